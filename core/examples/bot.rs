@@ -0,0 +1,28 @@
+use std::env;
+use tetris::bot::Bot;
+use tetris::*;
+
+/// auto-play games with the built-in bot:
+/// `cargo run --release --example bot -- [games] [pieces]`
+fn main() {
+    let mut args = env::args().skip(1);
+    let games: usize = args.next().and_then(|s| s.parse().ok()).unwrap_or(10);
+    let pieces: usize = args.next().and_then(|s| s.parse().ok()).unwrap_or(500);
+
+    let bot = Bot::default();
+    let mut total = 0;
+    for i in 0..games {
+        let mut game = Game::new();
+        let mut lines = 0;
+        let mut placed = 0;
+        while placed < pieces && !game.is_game_over() {
+            let (next, count) = bot.play(game);
+            game = next;
+            lines += count;
+            placed += 1;
+        }
+        total += lines;
+        println!("game {i}: {lines} lines, {placed} pieces");
+    }
+    println!("average: {} lines", total as f64 / games.max(1) as f64);
+}
@@ -0,0 +1,45 @@
+use std::env;
+use std::io;
+use std::process::{self, Command};
+use tetris::bot::Bot;
+use tetris::tbp::{self, Frontend};
+use tetris::*;
+
+const USAGE: &str = "usage: tbp engine | tbp play [-n pieces] <command> [args..]";
+
+/// Tetris Bot Protocol over stdin and stdout:
+/// `tbp engine` serves the built-in bot to a TBP frontend,
+/// `tbp play <command>` lets an external TBP bot play a game
+fn main() -> io::Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("engine") => tbp::serve(io::stdin().lock(), io::stdout().lock(), &Bot::default()),
+        Some("play") => {
+            let (pieces, command) = match &args[1..] {
+                [flag, n, command @ ..] if flag == "-n" => (n.parse().ok(), command),
+                command => (Some(500), command),
+            };
+            let (pieces, program, command_args) = match (pieces, command) {
+                (Some(pieces), [program, args @ ..]) => (pieces, program, args),
+                _ => usage(),
+            };
+
+            let (mut frontend, mut child) =
+                Frontend::spawn(Command::new(program).args(command_args))?;
+            let name = frontend.handshake()?;
+            let (game, lines) = frontend.play(Game::new(), pieces)?;
+            frontend.quit()?;
+            child.wait()?;
+
+            println!("{game}");
+            println!("{name}: {lines} lines");
+            Ok(())
+        }
+        _ => usage(),
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("{USAGE}");
+    process::exit(2)
+}
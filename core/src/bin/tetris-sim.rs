@@ -0,0 +1,231 @@
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use std::env;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tetris::bot::Bot;
+use tetris::movegen;
+use tetris::randomizer::Randomizer;
+use tetris::*;
+
+const USAGE: &str = "usage: tetris-sim [--games N] [--pieces N] [--bot heuristic|random] \
+[--randomizer random|bag] [--seed N] [--threads N]";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Player {
+    /// the built-in `Bot`
+    Heuristic,
+    /// any placement of the falling block, a baseline and an engine benchmark
+    Random,
+}
+
+#[derive(Debug)]
+struct Options {
+    games: usize,
+    pieces: usize,
+    player: Player,
+    randomizer: Randomizer,
+    seed: u64,
+    threads: usize,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self {
+            games: 100,
+            pieces: 1_000,
+            player: Player::Heuristic,
+            randomizer: Randomizer::Bag,
+            seed: 0,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+        };
+
+        while let Some(flag) = args.next() {
+            let value = args.next().ok_or(format!("missing value for {flag}"))?;
+            let number = || {
+                value
+                    .parse()
+                    .map_err(|_| format!("invalid {flag}: {value}"))
+            };
+            match flag.as_str() {
+                "--games" => options.games = number()?,
+                "--pieces" => options.pieces = number()?,
+                "--seed" => options.seed = number()? as u64,
+                "--threads" => options.threads = number()?.max(1),
+                "--randomizer" => options.randomizer = value.parse()?,
+                "--bot" => {
+                    options.player = match value.as_str() {
+                        "heuristic" => Player::Heuristic,
+                        "random" => Player::Random,
+                        _ => return Err(format!("unknown bot: {value}")),
+                    }
+                }
+                _ => return Err(format!("unknown option: {flag}")),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// result of a single game
+#[derive(Debug, Clone)]
+struct Record {
+    seed: u64,
+    lines: usize,
+    pieces: usize,
+    score: usize,
+    game_over: bool,
+    elapsed: Duration,
+}
+
+fn run(options: &Options, seed: u64) -> Record {
+    let bot = Bot::default();
+    let mut rng = StdRng::seed_from_u64(seed);
+    let start = Instant::now();
+
+    let mut game = Game::with_randomizer(options.randomizer, seed);
+    let mut pieces = 0;
+    while pieces < options.pieces && !game.is_game_over() {
+        game = match options.player {
+            Player::Heuristic => bot.play(game).0,
+            Player::Random => {
+                let game = match game.current() {
+                    Some(_) => game,
+                    None => game.advance_to_spawn(),
+                };
+                let placement = game.current().and_then(|t| {
+                    movegen::placements_with(game.board(), t, game.ruleset().rotation)
+                        .choose(&mut rng)
+                        .cloned()
+                });
+                match placement {
+                    Some(placement) => {
+                        let mut game = game;
+                        for step in placement.steps {
+                            game = game.input(step.into());
+                        }
+                        game.hard_drop().0
+                    }
+                    None => game,
+                }
+            }
+        };
+        pieces += 1;
+    }
+
+    Record {
+        seed,
+        lines: game.lines(),
+        pieces,
+        score: game.score(),
+        game_over: game.is_game_over(),
+        elapsed: start.elapsed(),
+    }
+}
+
+/// value at `p` percent of sorted values
+fn percentile(sorted: &[usize], p: usize) -> usize {
+    sorted[(sorted.len() - 1) * p / 100]
+}
+
+fn report(name: &str, values: &[usize]) {
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable();
+    let mean = sorted.iter().sum::<usize>() as f64 / sorted.len() as f64;
+    println!(
+        "{name:<8} mean {mean:>10.1}  min {:>8}  p25 {:>8}  p50 {:>8}  p75 {:>8}  max {:>8}",
+        sorted[0],
+        percentile(&sorted, 25),
+        percentile(&sorted, 50),
+        percentile(&sorted, 75),
+        sorted[sorted.len() - 1],
+    );
+}
+
+/// play seeded games in parallel and report their results:
+/// `cargo run --release --bin tetris-sim -- --games 100 --bot heuristic`
+fn main() {
+    let options = Options::parse(env::args().skip(1)).unwrap_or_else(|message| {
+        eprintln!("{message}\n{USAGE}");
+        process::exit(2)
+    });
+    if options.games == 0 {
+        eprintln!("{USAGE}");
+        process::exit(2)
+    }
+
+    let next = AtomicUsize::new(0);
+    let records = Mutex::new(Vec::with_capacity(options.games));
+    let start = Instant::now();
+    thread::scope(|scope| {
+        for _ in 0..options.threads.min(options.games) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= options.games {
+                    break;
+                }
+                let record = run(&options, options.seed + i as u64);
+                records.lock().unwrap().push(record);
+            });
+        }
+    });
+    let elapsed = start.elapsed();
+
+    let mut records = records.into_inner().unwrap();
+    records.sort_by_key(|record| record.seed);
+    for record in &records {
+        println!(
+            "seed {:>6}: {:>6} lines {:>6} pieces {:>8} score{}",
+            record.seed,
+            record.lines,
+            record.pieces,
+            record.score,
+            if record.game_over { "  game over" } else { "" },
+        );
+    }
+
+    let pieces: Vec<usize> = records.iter().map(|record| record.pieces).collect();
+    let total = pieces.iter().sum::<usize>() as f64;
+    let busy: f64 = records
+        .iter()
+        .map(|record| record.elapsed.as_secs_f64())
+        .sum();
+    println!();
+    println!(
+        "{} games, {:?} bot, {:?} randomizer, seeds {}..{}, {} threads",
+        options.games,
+        options.player,
+        options.randomizer,
+        options.seed,
+        options.seed + options.games as u64,
+        options.threads.min(options.games),
+    );
+    report(
+        "lines",
+        &records
+            .iter()
+            .map(|record| record.lines)
+            .collect::<Vec<_>>(),
+    );
+    report("pieces", &pieces);
+    report(
+        "score",
+        &records
+            .iter()
+            .map(|record| record.score)
+            .collect::<Vec<_>>(),
+    );
+    println!(
+        "game over {} / {}",
+        records.iter().filter(|record| record.game_over).count(),
+        options.games,
+    );
+    println!(
+        "{:.0} pieces/sec, {:.0} pieces/sec per thread, {:.2?} elapsed",
+        total / elapsed.as_secs_f64(),
+        total / busy,
+        elapsed,
+    );
+}
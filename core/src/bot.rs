@@ -0,0 +1,258 @@
+use super::{core, movegen, ruleset, Game, Input, Observer};
+
+/// weight of each board feature, positive values are preferred
+#[derive(Debug, PartialEq, Clone)]
+pub struct Weights {
+    /// sum of column heights
+    pub aggregate_height: f64,
+    /// empty cells below the top of their column
+    pub holes: f64,
+    /// sum of height differences between neighbouring columns
+    pub bumpiness: f64,
+    /// sum of well depths, counted as 1 + 2 + .. + depth for each well
+    pub wells: f64,
+    pub row_transitions: f64,
+    pub column_transitions: f64,
+    /// rows removed by the placement
+    pub lines: f64,
+}
+
+impl Default for Weights {
+    /// El-Tetris weights, with height and bumpiness from Yiyuan Lee's bot
+    fn default() -> Self {
+        Self {
+            aggregate_height: -0.51,
+            holes: -7.9,
+            bumpiness: -0.18,
+            wells: -3.39,
+            row_transitions: -3.22,
+            column_transitions: -9.35,
+            lines: 3.42,
+        }
+    }
+}
+
+/// placement picked by the bot
+#[derive(Debug, PartialEq, Clone)]
+pub struct Choice {
+    /// hold before moving the block
+    pub hold: bool,
+    pub placement: movegen::Placement,
+    pub score: f64,
+}
+
+impl Choice {
+    /// inputs from spawn to landing, the block locks on `Game::hard_drop`
+    /// or once the lock delay is over
+    pub fn inputs(&self) -> Vec<Input> {
+        let mut inputs = Vec::with_capacity(self.placement.steps.len() + 2);
+        if self.hold {
+            inputs.push(Input::Hold);
+        }
+        for step in &self.placement.steps {
+            inputs.push((*step).into());
+        }
+        inputs.push(movegen::Step::Drop.into());
+        inputs
+    }
+}
+
+/// heuristic player scoring every placement of the falling block
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Bot {
+    weights: Weights,
+}
+
+impl Bot {
+    pub fn new(weights: Weights) -> Self {
+        Self { weights }
+    }
+
+    /// score of a board after `lines` rows were removed
+    pub fn evaluate(&self, board: &core::Board, lines: usize) -> f64 {
+        let w = &self.weights;
+        let metrics = board.metrics();
+        let wells: usize = metrics.wells.iter().map(|d| d * (d + 1) / 2).sum();
+
+        w.aggregate_height * metrics.heights.iter().sum::<usize>() as f64
+            + w.holes * metrics.holes as f64
+            + w.bumpiness * metrics.bumpiness as f64
+            + w.wells * wells as f64
+            + w.row_transitions * metrics.row_transitions as f64
+            + w.column_transitions * metrics.column_transitions as f64
+            + w.lines * lines as f64
+    }
+
+    /// best placement for the falling block, trying hold when it is available
+    pub fn choose(&self, game: &Game) -> Option<Choice> {
+        let board = game.board();
+        let system = game.ruleset().rotation;
+        let mut best = self.best(board, game.current()?, false, system);
+
+        if game.can_hold() {
+            let other = game.hold().or_else(|| game.next().first().copied());
+            if let Some(choice) = other.and_then(|t| self.best(board, t, true, system)) {
+                if best.as_ref().is_none_or(|best| choice.score > best.score) {
+                    best = Some(choice);
+                }
+            }
+        }
+        best
+    }
+
+    /// every placement of the falling block and of the one hold would
+    /// bring in, best first
+    pub fn choices(&self, game: &Game) -> Vec<Choice> {
+        let board = game.board();
+        let system = game.ruleset().rotation;
+        let mut choices = match game.current() {
+            Some(t) => self.scored(board, t, false, system),
+            None => return Vec::new(),
+        };
+        if game.can_hold() {
+            if let Some(t) = game.hold().or_else(|| game.next().first().copied()) {
+                choices.extend(self.scored(board, t, true, system));
+            }
+        }
+        choices.sort_by(|a, b| b.score.total_cmp(&a.score));
+        choices
+    }
+
+    fn best(
+        &self,
+        board: &core::Board,
+        tetromino: core::Tetromino,
+        hold: bool,
+        system: ruleset::RotationSystem,
+    ) -> Option<Choice> {
+        self.scored(board, tetromino, hold, system)
+            .into_iter()
+            .max_by(|a, b| a.score.total_cmp(&b.score))
+    }
+
+    fn scored(
+        &self,
+        board: &core::Board,
+        tetromino: core::Tetromino,
+        hold: bool,
+        system: ruleset::RotationSystem,
+    ) -> Vec<Choice> {
+        movegen::placements_with(board, tetromino, system)
+            .into_iter()
+            .map(|placement| {
+                let (board, lines) = placement.lock(board.clone()).remove_valid_rows();
+                Choice {
+                    hold,
+                    score: self.evaluate(&board, lines),
+                    placement,
+                }
+            })
+            .collect()
+    }
+
+    /// spawn a block if needed, then place and lock it
+    ///
+    /// return the game and removed row-count
+    pub fn play(&self, game: Game) -> (Game, usize) {
+        self.play_with(game, &mut ())
+    }
+
+    pub fn play_with(&self, game: Game, observer: &mut dyn Observer) -> (Game, usize) {
+        let game = match game.current() {
+            Some(_) => game,
+            None => game.advance_to_spawn_with(observer),
+        };
+
+        let choice = match self.choose(&game) {
+            Some(choice) => choice,
+            None => return (game, 0),
+        };
+
+        let mut game = game;
+        for input in choice.inputs() {
+            game = game.input_with(input, observer);
+        }
+        game.hard_drop_with(observer)
+    }
+}
+
+#[cfg(test)]
+mod bot_tests {
+    use super::*;
+    use crate::{Block, Event};
+
+    #[test]
+    fn bot_evaluate() {
+        let bot = Bot::default();
+
+        // . . . .[][] . . . .
+        // . . . .[][] . . . .
+        let flat = core::Board::new().set_block(&core::Position::new(4, 18), core::Block::new_o());
+        // . . . .[][][] . . .
+        // . . . . .[] . . . .
+        let hole = core::Board::new().set_block(&core::Position::new(4, 18), core::Block::new_t());
+        assert!(bot.evaluate(&flat, 0) > bot.evaluate(&hole, 0));
+        assert!(bot.evaluate(&flat, 1) > bot.evaluate(&flat, 0));
+    }
+
+    #[test]
+    fn bot_choose() {
+        // . . . . . . . .[] .
+        // . . . . . . . .[] .
+        // . . . . . . . .[] .
+        // [][][][][][][][][] .
+        let mut game = Game::new();
+        game.board = core::Board::new()
+            .set_block(
+                &core::Position::new(0, 19),
+                core::Block::new_i().rotate(core::RotateDirection::Left),
+            )
+            .set_block(
+                &core::Position::new(4, 19),
+                core::Block::new_i().rotate(core::RotateDirection::Left),
+            )
+            .set_block(&core::Position::new(8, 16), core::Block::new_i());
+        game.block = Some(Block::spawn(core::Tetromino::I, &game.board));
+        game.held = true;
+
+        let choice = Bot::default().choose(&game).unwrap();
+        assert!(!choice.hold);
+        assert_eq!(choice.placement.position, core::Position::new(9, 16));
+
+        let (game, lines) = Bot::default().play(game);
+        assert_eq!(lines, 1);
+        assert_eq!(game.current(), None);
+    }
+
+    #[test]
+    fn bot_play_lock_delay() {
+        let bot = Bot::default();
+        let mut game = Game::with_ruleset(ruleset::Ruleset::guideline(), Some(0));
+        let mut events = Vec::new();
+        for _ in 0..10 {
+            game = bot.play_with(game, &mut events).0;
+        }
+        let locked = events
+            .iter()
+            .filter(|e| matches!(e, Event::PieceLocked { .. }))
+            .count();
+        assert_eq!(locked, 10);
+        assert_eq!(game.current(), None);
+    }
+
+    #[test]
+    fn bot_play_delays() {
+        let bot = Bot::default();
+        let mut game = Game::with_ruleset(ruleset::Ruleset::nes(), Some(0));
+        let mut events = Vec::new();
+        for _ in 0..10 {
+            game = bot.play_with(game, &mut events).0;
+        }
+        let locked = events
+            .iter()
+            .filter(|e| matches!(e, Event::PieceLocked { .. }))
+            .count();
+        assert_eq!(locked, 10);
+        assert!(game.phase().is_waiting());
+    }
+}
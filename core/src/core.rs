@@ -2,9 +2,10 @@ use rand::{
     distributions::{Distribution, Standard},
     Rng,
 };
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum Tetromino {
     /// []
     /// []
@@ -51,6 +52,17 @@ impl Distribution<Tetromino> for Standard {
 pub enum State {
     Empty,
     Block(Tetromino),
+    /// landing position of the falling block, never stored on the board
+    Ghost(Tetromino),
+    /// row pushed in from below, not part of any tetromino
+    Garbage,
+}
+
+impl State {
+    /// locked block or garbage
+    pub fn is_filled(&self) -> bool {
+        matches!(self, State::Block(_) | State::Garbage)
+    }
 }
 
 impl fmt::Display for State {
@@ -58,6 +70,8 @@ impl fmt::Display for State {
         match self {
             State::Empty => write!(f, " ."),
             State::Block(_) => write!(f, "[]"),
+            State::Ghost(_) => write!(f, "::"),
+            State::Garbage => write!(f, "##"),
         }
     }
 }
@@ -87,6 +101,7 @@ impl Size {
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod size_tests {
     use super::*;
 
@@ -122,7 +137,7 @@ pub enum RotateDirection {
     Right,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub struct Position {
     pub x: usize,
     pub y: usize,
@@ -239,7 +254,7 @@ impl Weight {
     }
 
     fn overlap(&self, p: &Position, other: Self) -> Result<Self, ()> {
-        if !self.on_inside(&p, &other) {
+        if !self.on_inside(p, &other) {
             return Err(());
         }
 
@@ -264,6 +279,7 @@ impl Weight {
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod weight_test {
     use super::*;
     #[test]
@@ -463,9 +479,9 @@ pub struct Block {
     source: Vec<State>,
 }
 
-impl Block {
-    pub fn new() -> Self {
-        match rand::random::<Tetromino>() {
+impl From<Tetromino> for Block {
+    fn from(t: Tetromino) -> Self {
+        match t {
             Tetromino::I => Block::new_i(),
             Tetromino::O => Block::new_o(),
             Tetromino::T => Block::new_t(),
@@ -475,7 +491,9 @@ impl Block {
             Tetromino::Z => Block::new_z(),
         }
     }
+}
 
+impl Block {
     pub fn new_i() -> Self {
         let size = Size::new(1, 4);
         let source = vec![
@@ -575,12 +593,21 @@ impl Block {
         Self { size, source }
     }
 
+    pub fn tetromino(&self) -> Tetromino {
+        for state in &self.source {
+            if let State::Block(t) = state {
+                return *t;
+            }
+        }
+        unreachable!("block without tetromino")
+    }
+
     fn weight(&self) -> Weight {
         let mut source = Vec::with_capacity(self.source.len());
         for i in 0..self.source.len() {
             match self.source[i] {
-                State::Empty => source.push(0),
                 State::Block(_) => source.push(1),
+                _ => source.push(0),
             }
         }
 
@@ -973,6 +1000,16 @@ mod block_tests {
         );
     }
 
+    #[test]
+    fn block_from_tetromino() {
+        assert_eq!(Block::from(Tetromino::T), Block::new_t());
+        assert_eq!(Block::from(Tetromino::Z).tetromino(), Tetromino::Z);
+        assert_eq!(
+            Block::new_j().rotate(RotateDirection::Left).tetromino(),
+            Tetromino::J
+        );
+    }
+
     #[test]
     fn block_state() {
         let block = Block::new_z();
@@ -994,7 +1031,12 @@ pub struct Board {
 
 impl Board {
     pub fn new() -> Self {
-        let size = Size::new(10, 20);
+        Self::with_size(10, 20)
+    }
+
+    /// empty board `width` cells wide and `height` high
+    pub fn with_size(width: usize, height: usize) -> Self {
+        let size = Size::new(width, height);
         let source = vec![State::Empty; size.len()];
         Self { size, source }
     }
@@ -1007,25 +1049,38 @@ impl Board {
         }
     }
 
+    /// weight of the whole board
+    #[cfg(test)]
     fn weight(&self) -> Weight {
-        let mut source = Vec::with_capacity(self.source.len());
-        for i in 0..self.source.len() {
-            source.push(match self.source[i] {
-                State::Empty => 0,
-                State::Block(_) => 1,
-            });
+        self.weight_at(&Position::new(0, 0), self.size)
+    }
+
+    /// weight of the `size` cells from `p`, cells off the board never fit
+    fn weight_at(&self, p: &Position, size: Size) -> Weight {
+        let mut source = Vec::with_capacity(size.len());
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let p = Position::new(p.x + x, p.y + y);
+                source.push(match self.size.on_inside(&p) {
+                    true if self.source[p.index(&self.size)].is_filled() => 1,
+                    true => 0,
+                    false => 2,
+                });
+            }
         }
-        Weight::new(self.size, source)
+        Weight::new(size, source)
     }
 
     pub fn can_change(&self, p: &Position, block: &Block) -> bool {
-        match self.weight().overlap(p, block.weight()) {
+        let block = block.weight();
+        let window = self.weight_at(p, block.size);
+        match window.overlap(&Position::new(0, 0), block) {
             Ok(w) => w.valid(),
             Err(()) => false,
         }
     }
 
-    fn set_state(mut self, p: &Position, state: State) -> Self {
+    pub fn set_state(mut self, p: &Position, state: State) -> Self {
         if self.size.on_inside(p) {
             self.source[p.index(&self.size)] = state;
         }
@@ -1044,9 +1099,14 @@ impl Board {
         self
     }
 
-    /// return self and valid-row-count
-    pub fn remove_valid_rows(mut self) -> (Self, usize) {
-        let mut remove_rows = Vec::with_capacity(self.size.y);
+    /// no cell is filled
+    pub fn is_empty(&self) -> bool {
+        self.source.iter().all(|state| *state == State::Empty)
+    }
+
+    /// rows filled with blocks, from top to bottom
+    pub fn valid_rows(&self) -> Vec<usize> {
+        let mut rows = Vec::with_capacity(self.size.y);
         for y in 0..self.size.y {
             let start = y * self.size.x;
             let end = start + self.size.x;
@@ -1054,14 +1114,20 @@ impl Board {
 
             let mut count_block = 0;
             for state in row {
-                if let State::Block(..) = state {
+                if state.is_filled() {
                     count_block += 1;
                 }
             }
             if count_block == self.size.x {
-                remove_rows.push(y);
+                rows.push(y);
             }
         }
+        rows
+    }
+
+    /// return self and valid-row-count
+    pub fn remove_valid_rows(mut self) -> (Self, usize) {
+        let remove_rows = self.valid_rows();
 
         for y in &remove_rows {
             for x in 0..self.size.x {
@@ -1086,9 +1152,308 @@ impl Board {
         }
         (self, remove_rows.len())
     }
+
+    /// hole of the bottom row, when it is a garbage row
+    fn garbage_hole(&self) -> Option<usize> {
+        let start = (self.size.y - 1) * self.size.x;
+        let row = &self.source[start..start + self.size.x];
+        if row
+            .iter()
+            .any(|state| !matches!(state, State::Empty | State::Garbage))
+        {
+            return None;
+        }
+        let mut holes = (0..self.size.x).filter(|x| row[*x] == State::Empty);
+        match (holes.next(), holes.next()) {
+            (Some(hole), None) => Some(hole),
+            _ => None,
+        }
+    }
+
+    /// shift the stack up and fill `rows` garbage rows from the bottom,
+    /// placing their holes by `strategy`
+    ///
+    /// return self and whether blocks were pushed over the top
+    pub fn push_garbage<R: Rng + ?Sized>(
+        mut self,
+        rows: usize,
+        strategy: &HoleStrategy,
+        rng: &mut R,
+    ) -> (Self, bool) {
+        let rows = rows.min(self.size.y);
+        let shifted = rows * self.size.x;
+        let top_out = self.source[..shifted]
+            .iter()
+            .any(|state| *state != State::Empty);
+
+        let width = self.size.x;
+        let mut holes: Vec<usize> = self.garbage_hole().into_iter().collect();
+        self.source.drain(..shifted);
+        for _ in 0..rows {
+            let hole = strategy.next(&holes, width, rng);
+            for x in 0..width {
+                self.source.push(if x == hole {
+                    State::Empty
+                } else {
+                    State::Garbage
+                });
+            }
+            holes.push(hole);
+        }
+        (self, top_out)
+    }
+}
+
+/// where the holes of incoming garbage rows go
+#[derive(Debug, PartialEq, Clone)]
+pub enum HoleStrategy {
+    /// every row keeps the hole of the previously pushed row, a clean well
+    Same,
+    /// a new column for each row
+    Random,
+    /// rows switch between the hole of the previously pushed row and
+    /// another column
+    Alternating,
+    /// the hole moves to another column with this percent chance per row
+    Messiness(u8),
+}
+
+impl HoleStrategy {
+    /// hole of the next row, `pushed` holding the holes of the previously
+    /// pushed rows in push order, starting with the bottom garbage row
+    /// already on the board
+    fn next<R: Rng + ?Sized>(&self, pushed: &[usize], width: usize, rng: &mut R) -> usize {
+        // no other column to move the hole to
+        if width <= 1 {
+            return 0;
+        }
+        let last = match pushed.last() {
+            Some(hole) => *hole,
+            None => return rng.gen_range(0..width),
+        };
+        let other = |rng: &mut R| (last + rng.gen_range(1..width)) % width;
+        match self {
+            HoleStrategy::Same => last,
+            HoleStrategy::Random => rng.gen_range(0..width),
+            HoleStrategy::Alternating => match pushed.len() {
+                1 => other(rng),
+                n => pushed[n - 2],
+            },
+            HoleStrategy::Messiness(percent) => {
+                if rng.gen_range(0..100) < *percent as usize {
+                    other(rng)
+                } else {
+                    last
+                }
+            }
+        }
+    }
+}
+
+/// features of a board, used to evaluate or describe a stack
+#[derive(Debug, PartialEq, Clone)]
+pub struct BoardMetrics {
+    /// height of each column, from the floor to its highest block
+    pub heights: Vec<usize>,
+    pub max_height: usize,
+    /// empty cells below the top of their column
+    pub holes: usize,
+    /// blocks above the lowest hole of their column
+    pub covered: usize,
+    /// sum of height differences between neighbouring columns
+    pub bumpiness: usize,
+    /// depth of each column below its lower neighbour, walls are endless
+    pub wells: Vec<usize>,
+    /// filled and empty changes along each row, walls count as filled
+    pub row_transitions: usize,
+    /// filled and empty changes down each column, the floor counts as filled
+    pub column_transitions: usize,
+    /// empty T shapes pointing down with both bottom corners and at least
+    /// one top corner filled
+    pub t_slots: usize,
+}
+
+impl Board {
+    fn filled(&self, x: usize, y: usize) -> bool {
+        self.state(&Position::new(x, y)).is_filled()
+    }
+
+    fn heights(&self) -> Vec<usize> {
+        (0..self.size.x)
+            .map(|x| {
+                (0..self.size.y)
+                    .find(|y| self.filled(x, *y))
+                    .map_or(0, |y| self.size.y - y)
+            })
+            .collect()
+    }
+
+    pub fn metrics(&self) -> BoardMetrics {
+        let heights = self.heights();
+
+        let mut holes = 0;
+        let mut covered = 0;
+        for (x, height) in heights.iter().enumerate() {
+            let top = self.size.y - height;
+            let lowest_hole = (top..self.size.y).rev().find(|y| !self.filled(x, *y));
+            holes += (top..self.size.y).filter(|y| !self.filled(x, *y)).count();
+            if let Some(hole) = lowest_hole {
+                covered += (top..hole).filter(|y| self.filled(x, *y)).count();
+            }
+        }
+
+        let wells = (0..self.size.x)
+            .map(|x| {
+                let left = x.checked_sub(1).map(|x| heights[x]);
+                let right = heights.get(x + 1).copied();
+                let side = match (left, right) {
+                    (Some(l), Some(r)) => l.min(r),
+                    (Some(h), None) | (None, Some(h)) => h,
+                    (None, None) => 0,
+                };
+                side.saturating_sub(heights[x])
+            })
+            .collect();
+
+        let mut row_transitions = 0;
+        for y in 0..self.size.y {
+            let mut last = true;
+            for x in 0..self.size.x {
+                let cell = self.filled(x, y);
+                if cell != last {
+                    row_transitions += 1;
+                }
+                last = cell;
+            }
+            if !last {
+                row_transitions += 1;
+            }
+        }
+
+        let mut column_transitions = 0;
+        for x in 0..self.size.x {
+            let mut last = false;
+            for y in 0..self.size.y {
+                let cell = self.filled(x, y);
+                if cell != last {
+                    column_transitions += 1;
+                }
+                last = cell;
+            }
+            if !last {
+                column_transitions += 1;
+            }
+        }
+
+        let mut t_slots = 0;
+        for y in 1..self.size.y.saturating_sub(1) {
+            for x in 1..self.size.x.saturating_sub(1) {
+                let empty = !self.filled(x, y)
+                    && !self.filled(x - 1, y)
+                    && !self.filled(x + 1, y)
+                    && !self.filled(x, y + 1);
+                let bottom = self.filled(x - 1, y + 1) && self.filled(x + 1, y + 1);
+                let top = self.filled(x - 1, y - 1) || self.filled(x + 1, y - 1);
+                if empty && bottom && top {
+                    t_slots += 1;
+                }
+            }
+        }
+
+        BoardMetrics {
+            max_height: heights.iter().copied().max().unwrap_or(0),
+            bumpiness: heights.windows(2).map(|w| w[0].abs_diff(w[1])).sum(),
+            heights,
+            holes,
+            covered,
+            wells,
+            row_transitions,
+            column_transitions,
+            t_slots,
+        }
+    }
 }
 
 #[cfg(test)]
+mod board_metrics_tests {
+    use super::*;
+
+    /// board with `rows` at the bottom, `[]` for a block and ` .` for empty
+    fn board(rows: &[&str]) -> Board {
+        let mut board = Board::new();
+        let top = board.size.y - rows.len();
+        for (y, row) in rows.iter().enumerate() {
+            for x in 0..board.size.x {
+                if &row[x * 2..x * 2 + 2] == "[]" {
+                    board = board.set_state(&Position::new(x, top + y), State::Block(Tetromino::O));
+                }
+            }
+        }
+        board
+    }
+
+    #[test]
+    fn board_metrics_empty() {
+        let metrics = Board::new().metrics();
+        assert_eq!(metrics.heights, vec![0; 10]);
+        assert_eq!(metrics.max_height, 0);
+        assert_eq!(metrics.holes, 0);
+        assert_eq!(metrics.bumpiness, 0);
+        assert_eq!(metrics.wells, vec![0; 10]);
+        assert_eq!(metrics.row_transitions, 2 * 20);
+        assert_eq!(metrics.column_transitions, 10);
+        assert_eq!(metrics.t_slots, 0);
+    }
+
+    #[test]
+    fn board_metrics_holes() {
+        let metrics = board(&[
+            " .[] . . . . . . . .",
+            "[][] . . . . . . . .",
+            " .[] . . . . . . . .",
+            "[] . . . . . . . . .",
+        ])
+        .metrics();
+        assert_eq!(metrics.heights, vec![3, 4, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(metrics.max_height, 4);
+        assert_eq!(metrics.holes, 2);
+        assert_eq!(metrics.covered, 4);
+        assert_eq!(metrics.bumpiness, 5);
+        assert_eq!(metrics.wells, vec![1, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn board_metrics_wells_and_transitions() {
+        let metrics = board(&[
+            "[][][] .[][][][][] .",
+            "[][][] .[][][][][] .",
+            "[][][] .[][][][][][]",
+        ])
+        .metrics();
+        assert_eq!(metrics.heights, vec![3, 3, 3, 0, 3, 3, 3, 3, 3, 1]);
+        assert_eq!(metrics.wells, vec![0, 0, 0, 3, 0, 0, 0, 0, 0, 2]);
+        assert_eq!(metrics.bumpiness, 3 + 3 + 2);
+        // 17 empty rows, two rows with both wells, one with the middle well
+        assert_eq!(metrics.row_transitions, 17 * 2 + 2 * 4 + 2);
+        // each column enters the stack once, column 3 reaches the floor empty
+        assert_eq!(metrics.column_transitions, 10);
+    }
+
+    #[test]
+    fn board_metrics_t_slots() {
+        let metrics = board(&[
+            "[][] . . . . . . . .",
+            " . . . . . . . . . .",
+            "[] .[][][][][][][][]",
+        ])
+        .metrics();
+        assert_eq!(metrics.t_slots, 1);
+        assert_eq!(metrics.holes, 3);
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod board_tests {
     use super::*;
 
@@ -1115,8 +1480,8 @@ mod board_tests {
         let mut source = Vec::with_capacity(board.source.len());
         for i in 0..board.source.len() {
             source.push(match board.source[i] {
-                State::Empty => 0,
-                State::Block(_) => 1,
+                State::Empty | State::Ghost(_) => 0,
+                State::Block(_) | State::Garbage => 1,
             });
         }
 
@@ -1150,8 +1515,65 @@ mod board_tests {
         let board = board.set_block(&Position::new(6, 18), Block::new_o());
         let board = board.set_block(&Position::new(8, 18), Block::new_o());
 
+        assert_eq!(board.valid_rows(), vec![18, 19]);
         assert_eq!(board.remove_valid_rows(), (Board::new(), 2));
     }
+
+    #[test]
+    fn board_push_garbage() {
+        let mut rng = rand::thread_rng();
+        let board = Board::new().set_block(&Position::new(0, 18), Block::new_o());
+        let (board, top_out) = board.push_garbage(2, &HoleStrategy::Random, &mut rng);
+        assert!(!top_out);
+        assert_eq!(
+            board.state(&Position::new(0, 16)),
+            State::Block(Tetromino::O)
+        );
+        for y in 18..20 {
+            let holes = (0..10)
+                .filter(|x| board.state(&Position::new(*x, y)) == State::Empty)
+                .count();
+            assert_eq!(holes, 1);
+        }
+        assert_eq!(board.valid_rows(), Vec::<usize>::new());
+
+        let (_, top_out) = board.push_garbage(17, &HoleStrategy::Random, &mut rng);
+        assert!(top_out);
+    }
+
+    #[test]
+    fn board_hole_strategy() {
+        let mut rng = rand::thread_rng();
+        let hole = |board: &Board, y| {
+            (0..10)
+                .find(|x| board.state(&Position::new(*x, y)) == State::Empty)
+                .unwrap()
+        };
+
+        let (board, _) = Board::new().push_garbage(1, &HoleStrategy::Random, &mut rng);
+        let first = hole(&board, 19);
+        let (board, _) = board.push_garbage(5, &HoleStrategy::Same, &mut rng);
+        assert!((14..20).all(|y| hole(&board, y) == first));
+
+        let (board, _) = board.push_garbage(4, &HoleStrategy::Alternating, &mut rng);
+        // the first pushed row ends up on top
+        let second = hole(&board, 16);
+        assert_ne!(second, first);
+        assert_eq!(hole(&board, 17), first);
+        assert_eq!(hole(&board, 18), second);
+        assert_eq!(hole(&board, 19), first);
+
+        let (board, _) = board.push_garbage(4, &HoleStrategy::Messiness(0), &mut rng);
+        assert!((16..20).all(|y| hole(&board, y) == first));
+        let (board, _) = board.push_garbage(4, &HoleStrategy::Messiness(100), &mut rng);
+        assert!((16..19).all(|y| hole(&board, y) != hole(&board, y + 1)));
+
+        // a single column is a hole in every row
+        for strategy in [HoleStrategy::Random, HoleStrategy::Alternating] {
+            let (board, _) = Board::with_size(1, 4).push_garbage(3, &strategy, &mut rng);
+            assert_eq!(board, Board::with_size(1, 4));
+        }
+    }
 }
 
 impl fmt::Display for Board {
@@ -0,0 +1,493 @@
+use super::{
+    core, movegen, ruleset::Ruleset, Event, Game, Input, InputMove, InputMoveDirection,
+    InputRotate, InputRotateDirection, NEXT_SIZE,
+};
+
+/// single key press of the low-level action space
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Key {
+    Noop,
+    Left,
+    Right,
+    /// one row down
+    SoftDrop,
+    /// down until the block lands, then lock it
+    HardDrop,
+    RotateLeft,
+    RotateRight,
+    Hold,
+}
+
+impl Key {
+    /// discrete action space, indexed in this order
+    pub const ALL: [Key; 8] = [
+        Key::Noop,
+        Key::Left,
+        Key::Right,
+        Key::SoftDrop,
+        Key::HardDrop,
+        Key::RotateLeft,
+        Key::RotateRight,
+        Key::Hold,
+    ];
+
+    fn input(&self) -> Option<Input> {
+        let input = match self {
+            Key::Noop => return None,
+            Key::Left => Input::Move(InputMove::new(InputMoveDirection::Left, 1)),
+            Key::Right => Input::Move(InputMove::new(InputMoveDirection::Right, 1)),
+            Key::SoftDrop => Input::Move(InputMove::new(InputMoveDirection::Bottom, 1)),
+            // locks through `Game::hard_drop`
+            Key::HardDrop => return None,
+            Key::RotateLeft => Input::Rotate(InputRotate::new(InputRotateDirection::Left, 1)),
+            Key::RotateRight => Input::Rotate(InputRotate::new(InputRotateDirection::Right, 1)),
+            Key::Hold => Input::Hold,
+        };
+        Some(input)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Action {
+    /// low-level: one key press, followed by gravity
+    Key(Key),
+    /// high-level: index into `Env::placements`, the block locks there
+    Placement(usize),
+}
+
+/// lock target of the high-level action space
+#[derive(Debug, PartialEq, Clone)]
+pub struct PlacementAction {
+    /// hold before moving the block
+    pub hold: bool,
+    pub placement: movegen::Placement,
+}
+
+/// piece index of the observation, `NONE` when there is no piece
+fn piece(t: Option<core::Tetromino>) -> u8 {
+    match t {
+        Some(core::Tetromino::I) => 0,
+        Some(core::Tetromino::O) => 1,
+        Some(core::Tetromino::T) => 2,
+        Some(core::Tetromino::J) => 3,
+        Some(core::Tetromino::L) => 4,
+        Some(core::Tetromino::S) => 5,
+        Some(core::Tetromino::Z) => 6,
+        None => Observation::NONE,
+    }
+}
+
+/// state seen by the agent
+///
+/// the encoding is stable:
+/// - `board` and `falling` are `width * height` bytes, row by row from the
+///   top-left, 1 for a filled cell and 0 for an empty one
+/// - pieces are indexed I, O, T, J, L, S, Z from 0 to 6, `NONE` is 7
+/// - `next` always holds `NEXT_SIZE` (5) pieces, `NONE` before the first spawn
+///   or past a shorter preview; a longer `Ruleset::preview` is cut off at
+///   `NEXT_SIZE`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Observation {
+    pub width: usize,
+    pub height: usize,
+    /// locked cells
+    pub board: Vec<u8>,
+    /// cells of the falling block
+    pub falling: Vec<u8>,
+    pub current: u8,
+    pub hold: u8,
+    pub next: Vec<u8>,
+    pub can_hold: bool,
+}
+
+impl Observation {
+    pub const NONE: u8 = 7;
+
+    fn new(game: &Game) -> Self {
+        let size = game.size();
+        let mut board = vec![0; size.x * size.y];
+        let mut falling = vec![0; size.x * size.y];
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let p = core::Position::new(x, y);
+                if game.board.state(&p).is_filled() {
+                    board[y * size.x + x] = 1;
+                }
+            }
+        }
+        if let Some(block) = &game.block {
+            for p in block.cells() {
+                falling[p.y * size.x + p.x] = 1;
+            }
+        }
+
+        let mut next: Vec<u8> = game.next.iter().map(|t| piece(Some(*t))).collect();
+        next.resize(NEXT_SIZE, Self::NONE);
+
+        Self {
+            width: size.x,
+            height: size.y,
+            board,
+            falling,
+            current: piece(game.current()),
+            hold: piece(game.hold()),
+            next,
+            can_hold: game.can_hold(),
+        }
+    }
+
+    /// flat feature vector: `board`, `falling`, one-hot of 8 for `current`,
+    /// `hold` and each of `next`, then `can_hold` as 0 or 1
+    pub fn features(&self) -> Vec<f32> {
+        let mut features = Vec::with_capacity(2 * self.board.len() + 8 * (2 + NEXT_SIZE) + 1);
+        features.extend(self.board.iter().map(|v| *v as f32));
+        features.extend(self.falling.iter().map(|v| *v as f32));
+        for piece in [self.current, self.hold].iter().chain(&self.next) {
+            features.extend((0..8).map(|i| if i == *piece { 1.0 } else { 0.0 }));
+        }
+        features.push(if self.can_hold { 1.0 } else { 0.0 });
+        features
+    }
+}
+
+/// reward of a step, summed over what happened in it
+#[derive(Debug, PartialEq, Clone)]
+pub struct Reward {
+    /// reward for removing 0, 1, 2, 3 and 4 rows at once
+    pub lines: [f64; 5],
+    /// each locked block
+    pub piece: f64,
+    /// the step ending the game
+    pub game_over: f64,
+    /// per hole added, negative to punish them
+    pub holes: f64,
+    /// per row the aggregate height grew
+    pub height: f64,
+}
+
+impl Default for Reward {
+    fn default() -> Self {
+        Self {
+            lines: [0.0, 1.0, 3.0, 5.0, 8.0],
+            piece: 0.0,
+            game_over: -1.0,
+            holes: 0.0,
+            height: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Config {
+    pub reward: Reward,
+    /// rules of the games, seeded by `Env::reset`
+    pub ruleset: Ruleset,
+    /// key presses between gravity ticks, 0 for no gravity
+    pub gravity: usize,
+}
+
+/// what happened in a step, beside the reward
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Info {
+    /// rows removed by the step
+    pub lines: usize,
+    /// blocks locked by the step
+    pub pieces: usize,
+    /// the placement index did not exist, the step did nothing
+    pub invalid: bool,
+    pub total_lines: usize,
+    pub total_pieces: usize,
+}
+
+/// gym-style environment over `Game`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Env {
+    config: Config,
+    game: Game,
+    keys: usize,
+    pieces: usize,
+}
+
+impl Env {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            game: Game::new(),
+            keys: 0,
+            pieces: 0,
+        }
+    }
+
+    /// start a new game, the same seed gives the same pieces
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.game = Game::with_ruleset(self.config.ruleset.clone(), Some(seed)).advance_to_spawn();
+        self.keys = 0;
+        self.pieces = 0;
+        self.observation()
+    }
+
+    pub fn observation(&self) -> Observation {
+        Observation::new(&self.game)
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// high-level actions for the falling block, then for the one hold
+    /// would bring in
+    pub fn placements(&self) -> Vec<PlacementAction> {
+        let current = match self.game.current() {
+            Some(t) => t,
+            None => return Vec::new(),
+        };
+        let board = self.game.board();
+        let system = self.game.ruleset().rotation;
+        let mut placements: Vec<_> = movegen::placements_with(board, current, system)
+            .into_iter()
+            .map(|placement| PlacementAction {
+                hold: false,
+                placement,
+            })
+            .collect();
+
+        if self.game.can_hold() {
+            if let Some(t) = self
+                .game
+                .hold()
+                .or_else(|| self.game.next().first().copied())
+            {
+                let held = movegen::placements_with(board, t, system);
+                placements.extend(held.into_iter().map(|placement| PlacementAction {
+                    hold: true,
+                    placement,
+                }));
+            }
+        }
+        placements
+    }
+
+    /// return the observation, reward, whether the game ended, and info
+    pub fn step(&mut self, action: Action) -> (Observation, f64, bool, Info) {
+        let mut info = Info::default();
+        if self.game.is_game_over() {
+            return self.finish(0.0, info);
+        }
+
+        let before = self.game.board.metrics();
+        let mut events = Vec::new();
+        let mut game = self.game.clone();
+        match action {
+            Action::Key(key) => {
+                if let Some(input) = key.input() {
+                    game = game.input_with(input, &mut events);
+                }
+                self.keys += 1;
+                let gravity =
+                    self.config.gravity > 0 && self.keys.is_multiple_of(self.config.gravity);
+                // keys stand in for frames, a landed block locks on the next
+                // gravity tick whatever the lock delay
+                let landed = game
+                    .block
+                    .as_ref()
+                    .is_some_and(|block| block.is_landed(&game.board));
+                if key == Key::HardDrop || (gravity && landed) {
+                    game = game.hard_drop_with(&mut events).0;
+                } else if gravity {
+                    game = game.deside_with(&mut events).0;
+                }
+            }
+            Action::Placement(index) => match self.placements().into_iter().nth(index) {
+                Some(action) => {
+                    if action.hold {
+                        game = game.input_with(Input::Hold, &mut events);
+                    }
+                    for step in action.placement.steps {
+                        game = game.input_with(step.into(), &mut events);
+                    }
+                    game = game.hard_drop_with(&mut events).0;
+                }
+                None => info.invalid = true,
+            },
+        }
+        // spawn right away, so the agent always sees a falling block
+        if game.current().is_none() {
+            game = game.advance_to_spawn_with(&mut events);
+        }
+        self.game = game;
+
+        for event in &events {
+            match event {
+                Event::PieceLocked { .. } => info.pieces += 1,
+                Event::LinesCleared { rows, .. } => info.lines += rows.len(),
+                _ => {}
+            }
+        }
+        self.pieces += info.pieces;
+
+        let r = &self.config.reward;
+        let after = self.game.board.metrics();
+        let height = |metrics: &core::BoardMetrics| metrics.heights.iter().sum::<usize>() as f64;
+        let mut reward = r.lines[info.lines.min(4)] + r.piece * info.pieces as f64;
+        reward += r.holes * (after.holes as f64 - before.holes as f64);
+        reward += r.height * (height(&after) - height(&before));
+        if self.game.is_game_over() {
+            reward += r.game_over;
+        }
+        self.finish(reward, info)
+    }
+
+    fn finish(&self, reward: f64, info: Info) -> (Observation, f64, bool, Info) {
+        let info = Info {
+            total_lines: self.game.lines(),
+            total_pieces: self.pieces,
+            ..info
+        };
+        (self.observation(), reward, self.game.is_game_over(), info)
+    }
+}
+
+#[cfg(test)]
+mod env_tests {
+    use super::*;
+
+    #[test]
+    fn env_reset() {
+        let mut env = Env::new(Config::default());
+        let observation = env.reset(1);
+        assert_eq!(observation, env.reset(1));
+        assert_eq!(observation.board, vec![0; 200]);
+        assert_eq!(observation.falling.iter().sum::<u8>(), 4);
+        assert_ne!(observation.current, Observation::NONE);
+        assert_eq!(observation.hold, Observation::NONE);
+        assert_eq!(observation.next.len(), NEXT_SIZE);
+        assert_eq!(observation.features().len(), 2 * 200 + 8 * 7 + 1);
+    }
+
+    #[test]
+    fn env_step_placement() {
+        let mut env = Env::new(Config {
+            reward: Reward {
+                piece: 0.5,
+                ..Reward::default()
+            },
+            ..Config::default()
+        });
+        env.reset(3);
+
+        let placements = env.placements();
+        let (observation, reward, done, info) = env.step(Action::Placement(0));
+        assert!(!done);
+        assert_eq!(info.pieces, 1);
+        assert_eq!(reward, 0.5);
+        assert_eq!(observation.board.iter().sum::<u8>(), 4);
+        assert!(!placements[0].hold);
+
+        let (_, reward, _, info) = env.step(Action::Placement(placements.len() * 2));
+        assert!(info.invalid);
+        assert_eq!(reward, 0.0);
+        assert_eq!(info.total_pieces, 1);
+    }
+
+    #[test]
+    fn env_step_key() {
+        let mut env = Env::new(Config {
+            gravity: 2,
+            ..Config::default()
+        });
+        env.reset(5);
+        let falling = |env: &Env| env.observation().falling;
+
+        let start = falling(&env);
+        env.step(Action::Key(Key::Noop));
+        assert_eq!(falling(&env), start);
+        env.step(Action::Key(Key::Noop));
+        assert_ne!(falling(&env), start);
+
+        let (observation, _, _, info) = env.step(Action::Key(Key::HardDrop));
+        assert_eq!(info.pieces, 1);
+        assert_eq!(observation.board.iter().sum::<u8>(), 4);
+        assert_eq!(observation.falling.iter().sum::<u8>(), 4);
+    }
+
+    #[test]
+    fn env_step_lock_delay() {
+        let mut env = Env::new(Config {
+            ruleset: Ruleset::guideline(),
+            ..Config::default()
+        });
+        env.reset(0);
+        let (observation, _, _, info) = env.step(Action::Key(Key::HardDrop));
+        assert_eq!(info.pieces, 1);
+        assert_eq!(observation.board.iter().sum::<u8>(), 4);
+
+        let (observation, _, _, info) = env.step(Action::Placement(0));
+        assert!(!info.invalid);
+        assert_eq!(info.pieces, 1);
+        assert_eq!(observation.board.iter().sum::<u8>(), 8);
+    }
+
+    #[test]
+    fn env_step_delays() {
+        let mut env = Env::new(Config {
+            ruleset: Ruleset::nes(),
+            ..Config::default()
+        });
+        env.reset(0);
+        for pieces in 1..=4 {
+            let (observation, _, done, info) = env.step(Action::Placement(0));
+            assert!(!info.invalid);
+            assert!(!done);
+            assert_eq!(info.total_pieces, pieces);
+            assert_ne!(observation.current, Observation::NONE);
+        }
+    }
+
+    #[test]
+    fn env_step_gravity() {
+        let mut env = Env::new(Config {
+            ruleset: Ruleset::guideline(),
+            gravity: 1,
+            ..Config::default()
+        });
+        env.reset(0);
+        let height = env.game().size().y;
+        let mut pieces = 0;
+        for _ in 0..height {
+            pieces += env.step(Action::Key(Key::Noop)).3.pieces;
+        }
+        assert_eq!(pieces, 1);
+    }
+
+    #[test]
+    fn env_step_key_delays() {
+        let mut env = Env::new(Config {
+            ruleset: Ruleset::nes(),
+            ..Config::default()
+        });
+        env.reset(0);
+        for pieces in 1..=4 {
+            let (observation, _, _, info) = env.step(Action::Key(Key::HardDrop));
+            assert_eq!(info.total_pieces, pieces);
+            assert_ne!(observation.current, Observation::NONE);
+            assert_eq!(observation.falling.iter().sum::<u8>(), 4);
+        }
+    }
+
+    #[test]
+    fn env_done() {
+        let mut env = Env::new(Config::default());
+        env.reset(0);
+        let mut done = false;
+        for _ in 0..200 {
+            let (_, reward, d, _) = env.step(Action::Key(Key::HardDrop));
+            done = d;
+            if done {
+                assert_eq!(reward, -1.0);
+                break;
+            }
+        }
+        assert!(done);
+        assert!(env.step(Action::Key(Key::Noop)).2);
+    }
+}
@@ -0,0 +1,199 @@
+use super::{core, movegen, ruleset, Block, InputMoveDirection};
+use std::collections::{HashMap, VecDeque};
+
+/// keys used on a locked block against the fewest reaching the same cells
+///
+/// keys are sideways taps, DAS to the wall and rotations, drops are free
+#[derive(Debug, PartialEq, Clone)]
+pub struct Finesse {
+    pub tetromino: core::Tetromino,
+    pub keys: usize,
+    pub optimal: usize,
+}
+
+impl Finesse {
+    /// keys beyond the optimal ones
+    pub fn faults(&self) -> usize {
+        self.keys.saturating_sub(self.optimal)
+    }
+}
+
+/// keys of a step, sideways moves and rotations, DAS being one
+fn is_key(step: &movegen::Step) -> bool {
+    !matches!(step, movegen::Step::MoveBottom | movegen::Step::Drop)
+}
+
+/// fewest keys from spawn to `cells` turning by `system`, `None` if the
+/// tetromino cannot lock there
+///
+/// a 0-1 BFS over the moves of `movegen`, drops being free
+pub fn optimal(
+    board: &core::Board,
+    tetromino: core::Tetromino,
+    cells: &[core::Position],
+    system: ruleset::RotationSystem,
+) -> Option<usize> {
+    let mut cells = cells.to_vec();
+    cells.sort_by_key(|p| (p.y, p.x));
+    let spawn = Block::spawn(tetromino, board);
+    if !spawn.can_change(board) {
+        return None;
+    }
+
+    let steps = movegen::Step::allowed(system);
+    let mut keys = HashMap::new();
+    let mut queue = VecDeque::new();
+    keys.insert((spawn.position.clone(), 0), 0);
+    queue.push_back((spawn, 0, 0));
+    while let Some((block, rotation, count)) = queue.pop_front() {
+        if keys[&(block.position.clone(), rotation)] < count {
+            continue;
+        }
+        if block.try_move(&InputMoveDirection::Bottom, board).is_none() {
+            let mut covered = block.cells();
+            covered.sort_by_key(|p| (p.y, p.x));
+            if covered == cells {
+                return Some(count);
+            }
+        }
+
+        for step in &steps {
+            let Some((next, rotation)) = step.apply(&block, rotation, board) else {
+                continue;
+            };
+            let key = is_key(step);
+            let count = count + usize::from(key);
+            let state = (next.position.clone(), rotation);
+            if keys.get(&state).is_some_and(|known| *known <= count) {
+                continue;
+            }
+            keys.insert(state, count);
+            match key {
+                true => queue.push_back((next, rotation, count)),
+                false => queue.push_front((next, rotation, count)),
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod finesse_tests {
+    use super::*;
+    use crate::handling::Action;
+    use crate::ruleset::RotationSystem::{Flip, Simple};
+    use crate::InputRotateDirection;
+    use crate::{Event, Game, Input, InputRotate};
+
+    fn lock(game: Game, inputs: Vec<Input>) -> (Game, Finesse) {
+        let mut events = Vec::new();
+        let game = inputs
+            .into_iter()
+            .fold(game, |game, input| game.input_with(input, &mut events));
+        let game = game
+            .input(movegen::Step::Drop.into())
+            .deside_with(&mut events)
+            .0;
+        let finesse = events.into_iter().find_map(|event| match event {
+            Event::Finesse(finesse) => Some(finesse),
+            _ => None,
+        });
+        (game, finesse.unwrap())
+    }
+
+    fn taps(step: movegen::Step, count: usize) -> Vec<Input> {
+        (0..count).map(|_| step.into()).collect()
+    }
+
+    #[test]
+    fn finesse_optimal() {
+        let board = core::Board::new();
+        let o = |x: usize| {
+            [
+                core::Position::new(x, 18),
+                core::Position::new(x + 1, 18),
+                core::Position::new(x, 19),
+                core::Position::new(x + 1, 19),
+            ]
+        };
+        // DAS to the wall, a tap, DAS and a tap back
+        assert_eq!(optimal(&board, core::Tetromino::O, &o(0), Flip), Some(1));
+        assert_eq!(optimal(&board, core::Tetromino::O, &o(4), Flip), Some(1));
+        assert_eq!(optimal(&board, core::Tetromino::O, &o(1), Flip), Some(2));
+        assert_eq!(optimal(&board, core::Tetromino::I, &o(0), Flip), None);
+
+        // a T upside down is one key with 180s, two without
+        let t = [
+            core::Position::new(4, 18),
+            core::Position::new(3, 19),
+            core::Position::new(4, 19),
+            core::Position::new(5, 19),
+        ];
+        assert_eq!(optimal(&board, core::Tetromino::T, &t, Flip), Some(1));
+        assert_eq!(optimal(&board, core::Tetromino::T, &t, Simple), Some(2));
+    }
+
+    #[test]
+    fn finesse_optimal_drops() {
+        // [] . . . . . . . . .
+        // . . .[][] . . . . .
+        // the fewest steps tap twice and drop, dropping onto the ledge,
+        // sliding left to the wall and dropping again takes one key
+        let board = core::Board::new()
+            .set_state(&core::Position::new(0, 9), core::State::Garbage)
+            .set_state(&core::Position::new(3, 10), core::State::Garbage)
+            .set_state(&core::Position::new(4, 10), core::State::Garbage);
+        let o = [
+            core::Position::new(1, 18),
+            core::Position::new(2, 18),
+            core::Position::new(1, 19),
+            core::Position::new(2, 19),
+        ];
+        let placement = movegen::placements(&board, core::Tetromino::O)
+            .into_iter()
+            .find(|p| p.position == core::Position::new(1, 18))
+            .unwrap();
+        let taps = placement.steps.iter().filter(|step| is_key(step)).count();
+        assert_eq!(taps, 2);
+        assert_eq!(optimal(&board, core::Tetromino::O, &o, Flip), Some(1));
+    }
+
+    #[test]
+    fn finesse_faults() {
+        let game = Game::with_sequence(&[core::Tetromino::O; 4]).deside().0;
+
+        // tapped to the wall
+        let (game, finesse) = lock(game, taps(movegen::Step::MoveLeft, 3));
+        assert_eq!((finesse.keys, finesse.optimal), (3, 1));
+        assert_eq!(finesse.faults(), 2);
+
+        // a held key counts once however far it shifts
+        let mut game = game.deside().0.input(Input::KeyDown(Action::Left));
+        for _ in 0..game.handling().das + 10 {
+            game = game.tick();
+        }
+        let game = game.input(Input::KeyUp(Action::Left));
+        let (game, finesse) = lock(game, Vec::new());
+        assert_eq!((finesse.keys, finesse.optimal), (1, 1));
+
+        // over and back again
+        let game = game.deside().0;
+        let mut inputs = taps(movegen::Step::MoveRight, 3);
+        inputs.extend(taps(movegen::Step::MoveLeft, 2));
+        let (game, finesse) = lock(game, inputs);
+        assert_eq!((finesse.keys, finesse.optimal), (5, 1));
+        assert_eq!(finesse.faults(), 4);
+
+        // an O never needs rotating
+        let game = game.deside().0;
+        let (game, finesse) = lock(
+            game,
+            vec![Input::Rotate(InputRotate::new(
+                InputRotateDirection::Right,
+                2,
+            ))],
+        );
+        assert_eq!(finesse.faults(), 2);
+        assert_eq!(game.finesse_faults(), 8);
+    }
+}
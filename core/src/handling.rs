@@ -0,0 +1,158 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// length of the frames `Game::tick` advances by
+pub const FRAME: Duration = Duration::from_nanos(16_666_667);
+
+/// key of `Input::KeyDown` and `Input::KeyUp`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Action {
+    Left,
+    Right,
+    /// falls `Handling::soft_drop_factor` times faster while held
+    SoftDrop,
+    /// down until the block lands, then lock it
+    HardDrop,
+    RotateLeft,
+    RotateRight,
+    /// rotate 180 degrees
+    Flip,
+    Hold,
+}
+
+/// how held keys repeat, in frames
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Handling {
+    /// delayed auto shift, frames a sideways key is held before it repeats
+    pub das: usize,
+    /// auto repeat rate, frames between repeats, 0 moves to the wall at once
+    pub arr: usize,
+    /// gravity multiplier while soft drop is held, 0 drops at once
+    pub soft_drop_factor: usize,
+}
+
+impl Default for Handling {
+    fn default() -> Self {
+        Self {
+            das: 10,
+            arr: 2,
+            soft_drop_factor: 20,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum Direction {
+    Left,
+    Right,
+}
+
+/// keys held down and the auto shift timers
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub(crate) struct Keys {
+    pub(crate) left: bool,
+    pub(crate) right: bool,
+    pub(crate) soft_drop: bool,
+    /// the sideways key pressed last and still held
+    pub(crate) direction: Option<Direction>,
+    /// frames `direction` has been held, up to DAS
+    pub(crate) charge: usize,
+    /// frames since the last repeat
+    pub(crate) repeat: usize,
+    /// `direction` moved the falling block since it was pressed or took
+    /// over, and was counted as a key
+    pub(crate) shifted: bool,
+}
+
+impl Keys {
+    pub(crate) fn press(&mut self, direction: Direction) {
+        match direction {
+            Direction::Left => self.left = true,
+            Direction::Right => self.right = true,
+        }
+        self.direction = Some(direction);
+        self.charge = 0;
+        self.repeat = 0;
+        self.shifted = false;
+    }
+
+    /// the other sideways key takes over if it is still held
+    pub(crate) fn release(&mut self, direction: Direction) {
+        match direction {
+            Direction::Left => self.left = false,
+            Direction::Right => self.right = false,
+        }
+        if self.direction == Some(direction) {
+            self.direction = match (self.left, self.right) {
+                (true, _) => Some(Direction::Left),
+                (_, true) => Some(Direction::Right),
+                _ => None,
+            };
+            self.charge = 0;
+            self.repeat = 0;
+            self.shifted = false;
+        }
+    }
+
+    /// advance a frame, return the cells to shift by, `usize::MAX` for
+    /// the wall
+    ///
+    /// the timers run without a falling block too, so DAS charged during
+    /// the entry delay shifts the next block as soon as it spawns
+    pub(crate) fn tick(&mut self, handling: &Handling) -> Option<(Direction, usize)> {
+        let direction = self.direction?;
+        if self.charge < handling.das {
+            self.charge += 1;
+            if self.charge < handling.das {
+                return None;
+            }
+        } else {
+            self.repeat += 1;
+            if handling.arr > 0 && self.repeat < handling.arr {
+                return None;
+            }
+        }
+        self.repeat = 0;
+        match handling.arr {
+            0 => Some((direction, usize::MAX)),
+            _ => Some((direction, 1)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod handling_tests {
+    use super::*;
+
+    #[test]
+    fn handling_keys() {
+        let handling = Handling {
+            das: 3,
+            arr: 2,
+            soft_drop_factor: 20,
+        };
+        let mut keys = Keys::default();
+        assert_eq!(keys.tick(&handling), None);
+
+        keys.press(Direction::Left);
+        let shifts: Vec<_> = (0..7).map(|_| keys.tick(&handling)).collect();
+        let left = Some((Direction::Left, 1));
+        assert_eq!(shifts, vec![None, None, left, None, left, None, left]);
+
+        // the newer key wins, and the older one takes over again
+        keys.press(Direction::Right);
+        assert_eq!(keys.tick(&handling), None);
+        keys.release(Direction::Right);
+        assert_eq!(keys.direction, Some(Direction::Left));
+        assert_eq!(keys.charge, 0);
+        keys.release(Direction::Left);
+        assert_eq!(keys.direction, None);
+
+        let instant = Handling { arr: 0, ..handling };
+        keys.press(Direction::Right);
+        let shifts: Vec<_> = (0..4).map(|_| keys.tick(&instant)).collect();
+        let wall = Some((Direction::Right, usize::MAX));
+        assert_eq!(shifts, vec![None, None, wall, wall]);
+    }
+}
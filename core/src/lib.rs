@@ -1,9 +1,31 @@
 use std::fmt;
 
+pub mod bot;
 mod core;
+pub mod env;
+pub mod finesse;
+pub mod handling;
+pub mod mode;
+pub mod movegen;
+pub mod pc;
+pub mod puzzle;
+pub mod randomizer;
+pub mod ruleset;
+pub mod scoreboard;
+pub mod stats;
+pub mod tbp;
+pub mod timing;
+pub mod versus;
 
 pub type State = core::State;
 pub type Position = core::Position;
+pub type Tetromino = core::Tetromino;
+pub type Board = core::Board;
+pub type BoardMetrics = core::BoardMetrics;
+pub type HoleStrategy = core::HoleStrategy;
+
+/// number of tetrominos shown in the next queue
+const NEXT_SIZE: usize = 5;
 
 #[derive(Debug, PartialEq)]
 pub enum InputMoveDirection {
@@ -49,12 +71,26 @@ mod input_move_tests {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum InputRotateDirection {
     Left,
     Right,
+    /// 180 degrees at once, kicked by `FLIP_KICKS`
+    Flip,
 }
 
+/// offsets tried in order when a 180 rotation does not fit in place, x to
+/// the right and y down, the SRS+ ones of TETR.IO
+///
+/// a list for each pair of SRS states, indexed by the state the block
+/// starts in (see `Block::srs_state`): 0 to 2, R to L, 2 to 0 and L to R
+const FLIP_KICKS: [[(isize, isize); 6]; 4] = [
+    [(0, 0), (0, -1), (1, -1), (-1, -1), (1, 0), (-1, 0)],
+    [(0, 0), (1, 0), (1, -2), (1, -1), (0, -2), (0, -1)],
+    [(0, 0), (0, 1), (-1, 1), (1, 1), (-1, 0), (1, 0)],
+    [(0, 0), (-1, 0), (-1, -2), (-1, -1), (0, -2), (0, -1)],
+];
+
 #[derive(Debug, PartialEq)]
 pub struct InputRotate {
     direction: InputRotateDirection,
@@ -95,6 +131,111 @@ mod input_rotate_tests {
 pub enum Input {
     Move(InputMove),
     Rotate(InputRotate),
+    Hold,
+    /// press a key, held sideways keys repeat on `Game::tick`
+    KeyDown(handling::Action),
+    KeyUp(handling::Action),
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ClearKind {
+    Single,
+    Double,
+    Triple,
+    Tetris,
+}
+
+impl ClearKind {
+    fn new(count: usize) -> Option<Self> {
+        match count {
+            0 => None,
+            1 => Some(ClearKind::Single),
+            2 => Some(ClearKind::Double),
+            3 => Some(ClearKind::Triple),
+            _ => Some(ClearKind::Tetris),
+        }
+    }
+
+    /// removed rows
+    pub fn rows(&self) -> usize {
+        match self {
+            ClearKind::Single => 1,
+            ClearKind::Double => 2,
+            ClearKind::Triple => 3,
+            ClearKind::Tetris => 4,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TSpin {
+    /// the 3-corner rule holds, but one of the corners the T points to is open
+    Mini,
+    Full,
+}
+
+/// what a lock removing rows achieved
+#[derive(Debug, PartialEq, Clone)]
+pub struct Clear {
+    pub kind: ClearKind,
+    pub t_spin: Option<TSpin>,
+    /// clearing locks right before this one, 0 when the combo starts
+    pub combo: usize,
+    /// a tetris or T-spin following another one, with no easier clear between
+    pub back_to_back: bool,
+    /// no block is left on the board
+    pub all_clear: bool,
+}
+
+impl Clear {
+    /// tetrises and T-spins keep a back-to-back chain going
+    pub fn is_difficult(&self) -> bool {
+        self.kind == ClearKind::Tetris || self.t_spin.is_some()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Event {
+    PieceSpawned(core::Tetromino),
+    /// the falling block was moved by an input
+    PieceMoved {
+        position: core::Position,
+    },
+    /// `kick` is the index of the offset that made the rotation fit,
+    /// 0 when the block rotated in place
+    PieceRotated {
+        kick: usize,
+    },
+    PieceLocked {
+        tetromino: core::Tetromino,
+        position: core::Position,
+    },
+    /// `rows` are counted from the top, before they were removed
+    LinesCleared {
+        rows: Vec<usize>,
+        clear: Clear,
+    },
+    /// the tetromino put in hold
+    Hold(core::Tetromino),
+    /// keys used on the block just locked
+    Finesse(finesse::Finesse),
+    LevelUp(usize),
+    GameOver,
+}
+
+/// receives the events of a game as they happen
+pub trait Observer {
+    fn notify(&mut self, event: Event);
+}
+
+impl Observer for () {
+    fn notify(&mut self, _: Event) {}
+}
+
+impl Observer for Vec<Event> {
+    fn notify(&mut self, event: Event) {
+        self.push(event);
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -108,6 +249,54 @@ impl Block {
         Self { block, position }
     }
 
+    /// top of `board`, centered for a 4 cells wide block
+    fn spawn(tetromino: core::Tetromino, board: &core::Board) -> Self {
+        let x = board.size.x.saturating_sub(4) / 2;
+        Self::new(core::Position::new(x, 0), core::Block::from(tetromino))
+    }
+
+    /// right rotations from the spawn orientation, the lowest one when
+    /// rotating shows the same shape again
+    fn orientation(&self) -> usize {
+        let mut shape = core::Block::from(self.block.tetromino());
+        for r in 0..4 {
+            if shape == self.block {
+                return r;
+            }
+            shape = shape.rotate(core::RotateDirection::Right);
+        }
+        0
+    }
+
+    /// SRS state of the block, 0 for the flat side down up to 3 for L,
+    /// these spawn pointing down (T) or standing (I, J and L)
+    ///
+    /// shapes showing twice in a turn (I, O, S and Z) take the lower state
+    fn srs_state(&self) -> usize {
+        let spawn = match self.block.tetromino() {
+            core::Tetromino::T => 2,
+            core::Tetromino::J => 3,
+            core::Tetromino::I | core::Tetromino::L => 1,
+            core::Tetromino::O | core::Tetromino::S | core::Tetromino::Z => 0,
+        };
+        (spawn + self.orientation()) % 4
+    }
+
+    fn cells(&self) -> Vec<core::Position> {
+        let mut cells = Vec::with_capacity(4);
+        for y in 0..self.block.size.y {
+            for x in 0..self.block.size.x {
+                if let core::State::Block(_) = self.block.state(&core::Position::new(x, y)) {
+                    cells.push(core::Position::new(
+                        self.position.x + x,
+                        self.position.y + y,
+                    ));
+                }
+            }
+        }
+        cells
+    }
+
     fn on_inside(&self, p: &core::Position) -> bool {
         p.x >= self.position.x
             && p.x < self.block.size.x + self.position.x
@@ -147,22 +336,56 @@ impl Block {
         self
     }
 
-    fn reverse_rotate(mut self, direction: &InputRotateDirection) -> Self {
-        let direction = match direction {
-            InputRotateDirection::Left => core::RotateDirection::Right,
-            InputRotateDirection::Right => core::RotateDirection::Left,
+    fn rotate(mut self, direction: &InputRotateDirection) -> Self {
+        self.block = match direction {
+            InputRotateDirection::Left => self.block.rotate(core::RotateDirection::Left),
+            InputRotateDirection::Right => self.block.rotate(core::RotateDirection::Right),
+            InputRotateDirection::Flip => self
+                .block
+                .rotate(core::RotateDirection::Right)
+                .rotate(core::RotateDirection::Right),
         };
-        self.block = self.block.rotate(direction);
         self
     }
 
-    fn rotate(mut self, direction: &InputRotateDirection) -> Self {
-        let direction = match direction {
-            InputRotateDirection::Left => core::RotateDirection::Left,
-            InputRotateDirection::Right => core::RotateDirection::Right,
+    fn kick(&self, (x, y): (isize, isize)) -> Option<Self> {
+        let position = core::Position::new(
+            self.position.x.checked_add_signed(x)?,
+            self.position.y.checked_add_signed(y)?,
+        );
+        Some(Self::new(position, self.block.clone()))
+    }
+
+    /// the moved block, if it fits on the board
+    fn try_move(&self, direction: &InputMoveDirection, board: &core::Board) -> Option<Self> {
+        let block = self.clone().r#move(direction);
+        if block.position == self.position || !block.can_change(board) {
+            return None;
+        }
+        Some(block)
+    }
+
+    /// resting on the stack or the floor
+    fn is_landed(&self, board: &core::Board) -> bool {
+        self.try_move(&InputMoveDirection::Bottom, board).is_none()
+    }
+
+    /// the rotated block and the index of the kick it took, if it fits on
+    /// the board
+    fn try_rotate(
+        &self,
+        direction: &InputRotateDirection,
+        board: &core::Board,
+    ) -> Option<(Self, usize)> {
+        let kicks: &[(isize, isize)] = match direction {
+            InputRotateDirection::Flip => &FLIP_KICKS[self.srs_state()],
+            _ => &[(0, 0)],
         };
-        self.block = self.block.rotate(direction);
-        self
+        let block = self.clone().rotate(direction);
+        kicks
+            .iter()
+            .enumerate()
+            .find_map(|(i, offset)| Some((block.kick(*offset).filter(|b| b.can_change(board))?, i)))
     }
 }
 
@@ -170,24 +393,268 @@ impl Block {
 pub struct Game {
     board: core::Board,
     block: Option<Block>,
+    next: Vec<core::Tetromino>,
+    hold: Option<core::Tetromino>,
+    /// hold was used by the current block
+    held: bool,
+    ruleset: ruleset::Ruleset,
+    lines: usize,
+    score: usize,
+    /// the last move of the falling block was a rotation
+    rotated: bool,
+    /// sideways keys and rotations pressed for the falling block
+    keys: usize,
+    /// keys beyond the optimal ones, over all locked blocks
+    finesse_faults: usize,
+    handling: handling::Handling,
+    held_keys: handling::Keys,
+    /// frames the falling block has rested on the stack
+    landed: usize,
+    phase: timing::Phase,
+    /// rotation and hold pressed while waiting for the next block
+    buffer: timing::Buffer,
+    /// consecutive clearing locks, 0 after a lock removing no row
+    combo: usize,
+    /// the last clear was a tetris or a T-spin
+    back_to_back: bool,
+    game_over: bool,
+    generator: randomizer::Generator,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Game {
     pub fn new() -> Self {
-        let board = core::Board::new();
-        let block = None;
-        Self { board, block }
+        Self::with_ruleset(ruleset::Ruleset::default(), None)
+    }
+
+    /// game played by `ruleset`, drawing its tetrominos from `seed` if any
+    pub fn with_ruleset(ruleset: ruleset::Ruleset, seed: Option<u64>) -> Self {
+        Self {
+            board: core::Board::with_size(ruleset.width, ruleset.height),
+            block: None,
+            next: Vec::with_capacity(ruleset.preview + 1),
+            hold: None,
+            held: false,
+            lines: 0,
+            score: 0,
+            rotated: false,
+            keys: 0,
+            finesse_faults: 0,
+            handling: ruleset.handling,
+            held_keys: handling::Keys::default(),
+            landed: 0,
+            phase: timing::Phase::default(),
+            buffer: timing::Buffer::default(),
+            combo: 0,
+            back_to_back: false,
+            game_over: false,
+            generator: randomizer::Generator::new(ruleset.randomizer, seed),
+            ruleset,
+        }
+    }
+
+    /// game drawing its tetrominos from `seed`, the same seed gives the same pieces
+    pub fn with_randomizer(randomizer: randomizer::Randomizer, seed: u64) -> Self {
+        let ruleset = ruleset::Ruleset {
+            randomizer,
+            ..ruleset::Ruleset::default()
+        };
+        Self::with_ruleset(ruleset, Some(seed))
+    }
+
+    /// game drawing the tetrominos of `sequence` in order and no more,
+    /// no block spawns once they are used up
+    pub fn with_sequence(sequence: &[core::Tetromino]) -> Self {
+        Self {
+            generator: randomizer::Generator::fixed(sequence),
+            ..Self::new()
+        }
+    }
+
+    /// game where hold does nothing
+    pub fn without_hold(mut self) -> Self {
+        self.ruleset.hold = false;
+        self
+    }
+
+    /// game repeating held keys with `handling`
+    pub fn with_handling(self, handling: handling::Handling) -> Self {
+        Self { handling, ..self }
+    }
+
+    /// game waiting `delays` between a lock and the next spawn, the next
+    /// block spawns on `Game::tick` once they run out
+    pub fn with_delays(mut self, delays: timing::Delays) -> Self {
+        self.ruleset.delays = delays;
+        self
     }
 
     pub fn input(self, input: Input) -> Self {
+        self.input_with(input, &mut ())
+    }
+
+    pub fn input_with(self, input: Input, observer: &mut dyn Observer) -> Self {
         match input {
-            Input::Move(input) => self.input_move(input),
-            Input::Rotate(input) => self.input_rotate(input),
+            Input::Move(input) => {
+                let (mut game, sideways) = self.move_with(input, observer);
+                if sideways {
+                    game.keys += 1;
+                }
+                game
+            }
+            Input::Rotate(input) if self.phase.is_waiting() => {
+                let mut game = self;
+                if input.can_rotate() {
+                    game.buffer.rotation = Some(input.direction);
+                }
+                game
+            }
+            Input::Hold if self.phase.is_waiting() => {
+                let mut game = self;
+                game.buffer.hold = game.ruleset.hold;
+                game
+            }
+            Input::Rotate(input) => {
+                let (mut game, kicks) = self.input_rotate(input, Vec::new());
+                for kick in kicks {
+                    game.rotated = true;
+                    game.keys += 1;
+                    observer.notify(Event::PieceRotated { kick });
+                }
+                game
+            }
+            Input::Hold => self.input_hold(observer),
+            Input::KeyDown(action) => self.key_down(action, observer),
+            Input::KeyUp(action) => self.key_up(action),
+        }
+    }
+
+    /// move the falling block, return whether it went sideways
+    fn move_with(self, input: InputMove, observer: &mut dyn Observer) -> (Self, bool) {
+        let position = self.block.as_ref().map(|block| block.position.clone());
+        let mut game = self.input_move(input);
+        let mut sideways = false;
+        if let (Some(block), Some(position)) = (&game.block, position) {
+            if block.position != position {
+                sideways = block.position.x != position.x;
+                game.rotated = false;
+                observer.notify(Event::PieceMoved {
+                    position: block.position.clone(),
+                });
+            }
+        }
+        (game, sideways)
+    }
+
+    /// shift by the held sideways key, a key the first time it moves the
+    /// falling block, repeats are free
+    fn shift(
+        self,
+        direction: handling::Direction,
+        amount: usize,
+        observer: &mut dyn Observer,
+    ) -> Self {
+        let input = match direction {
+            handling::Direction::Left => InputMove::new(InputMoveDirection::Left, amount),
+            handling::Direction::Right => InputMove::new(InputMoveDirection::Right, amount),
+        };
+        let (mut game, sideways) = self.move_with(input, observer);
+        if sideways && !game.held_keys.shifted {
+            game.held_keys.shifted = true;
+            game.keys += 1;
+        }
+        game
+    }
+
+    fn key_down(mut self, action: handling::Action, observer: &mut dyn Observer) -> Self {
+        let input = match action {
+            handling::Action::Left | handling::Action::Right => {
+                let direction = match action {
+                    handling::Action::Left => handling::Direction::Left,
+                    _ => handling::Direction::Right,
+                };
+                self.held_keys.press(direction);
+                return self.shift(direction, 1, observer);
+            }
+            handling::Action::SoftDrop => {
+                self.held_keys.soft_drop = true;
+                return self;
+            }
+            handling::Action::HardDrop => return self.hard_drop_with(observer).0,
+            handling::Action::RotateLeft => {
+                Input::Rotate(InputRotate::new(InputRotateDirection::Left, 1))
+            }
+            handling::Action::RotateRight => {
+                Input::Rotate(InputRotate::new(InputRotateDirection::Right, 1))
+            }
+            handling::Action::Flip => {
+                Input::Rotate(InputRotate::new(InputRotateDirection::Flip, 1))
+            }
+            handling::Action::Hold => Input::Hold,
+        };
+        self.input_with(input, observer)
+    }
+
+    fn key_up(mut self, action: handling::Action) -> Self {
+        match action {
+            handling::Action::Left => self.held_keys.release(handling::Direction::Left),
+            handling::Action::Right => self.held_keys.release(handling::Direction::Right),
+            handling::Action::SoftDrop => self.held_keys.soft_drop = false,
+            _ => {}
+        }
+        self
+    }
+
+    /// advance a `handling::FRAME`, repeating held keys
+    pub fn tick(self) -> Self {
+        self.tick_with(&mut ())
+    }
+
+    pub fn tick_with(mut self, observer: &mut dyn Observer) -> Self {
+        if self.game_over {
+            return self;
+        }
+
+        // the next block spawns once the delays run out, or on the frame
+        // after a lock without delays
+        let ended = self.phase.tick(&self.ruleset.delays);
+        if ended || (self.block.is_none() && !self.phase.is_waiting()) {
+            self = self.spawn(observer);
+        }
+        let mut game = match self.held_keys.tick(&self.handling) {
+            Some((direction, amount)) => self.shift(direction, amount, observer),
+            None => self,
+        };
+        if game.held_keys.soft_drop && game.handling.soft_drop_factor == 0 {
+            game = game.input_with(movegen::Step::Drop.into(), observer);
+        }
+        game.tick_lock(observer)
+    }
+
+    /// count a frame of lock delay for a landed block, lock it once the
+    /// delay is over
+    fn tick_lock(mut self, observer: &mut dyn Observer) -> Self {
+        let landed = match &self.block {
+            Some(block) => block.is_landed(&self.board),
+            None => false,
+        };
+        if !landed || self.ruleset.lock_delay == 0 {
+            return self;
+        }
+        self.landed += 1;
+        match self.landed >= self.ruleset.lock_delay {
+            true => self.deside_with(observer).0,
+            false => self,
         }
     }
 
     fn input_move(mut self, input: InputMove) -> Self {
-        if let None = self.block {
+        if self.block.is_none() {
             return self;
         }
 
@@ -195,58 +662,258 @@ impl Game {
             return self;
         }
 
+        let position = self.block.as_ref().unwrap().position.clone();
         let block = self.block.unwrap().r#move(&input.direction);
 
         if !block.can_change(&self.board) {
             self.block = Some(block.reverse_move(&input.direction));
             return self;
         }
+        // against the left wall the position saturates at 0
+        if block.position == position {
+            self.block = Some(block);
+            return self;
+        }
 
         self.block = Some(block);
-        return self.input_move(input.r#move());
+        self.input_move(input.r#move())
     }
 
-    fn input_rotate(mut self, input: InputRotate) -> Self {
-        if let None = self.block {
-            return self;
+    /// return self and the kick of each rotation done
+    fn input_rotate(mut self, input: InputRotate, mut kicks: Vec<usize>) -> (Self, Vec<usize>) {
+        if self.block.is_none() {
+            return (self, kicks);
         }
 
         if !input.can_rotate() {
+            return (self, kicks);
+        }
+
+        let flip = input.direction == InputRotateDirection::Flip;
+        if flip && self.ruleset.rotation != ruleset::RotationSystem::Flip {
+            return (self, kicks);
+        }
+
+        let block = self.block.as_ref().unwrap();
+        match block.try_rotate(&input.direction, &self.board) {
+            Some((block, kick)) => {
+                self.block = Some(block);
+                kicks.push(kick);
+                self.input_rotate(input.rotate(), kicks)
+            }
+            None => (self, kicks),
+        }
+    }
+
+    /// swap the current block with the held one, at most once per block
+    fn input_hold(mut self, observer: &mut dyn Observer) -> Self {
+        if self.held || !self.ruleset.hold {
             return self;
         }
 
-        let block = self.block.unwrap().rotate(&input.direction);
+        let tetromino = match &self.block {
+            Some(block) => block.block.tetromino(),
+            None => return self,
+        };
 
-        if !block.can_change(&self.board) {
-            self.block = Some(block.reverse_rotate(&input.direction));
+        self.block = None;
+        observer.notify(Event::Hold(tetromino));
+        let game = match self.hold.replace(tetromino) {
+            Some(t) => self.spawn_block(t, observer),
+            None => self.spawn(observer),
+        };
+        Self { held: true, ..game }
+    }
+
+    fn spawn(mut self, observer: &mut dyn Observer) -> Self {
+        while self.next.len() <= self.ruleset.preview {
+            match self.generator.next() {
+                Some(tetromino) => self.next.push(tetromino),
+                None => break,
+            }
+        }
+        if self.next.is_empty() {
             return self;
         }
+        let tetromino = self.next.remove(0);
+        self.spawn_block(tetromino, observer)
+    }
 
-        self.block = Some(block);
-        self.input_rotate(input.rotate())
+    fn spawn_block(mut self, tetromino: core::Tetromino, observer: &mut dyn Observer) -> Self {
+        let block = Block::spawn(tetromino, &self.board);
+        if block.can_change(&self.board) {
+            self.block = Some(block);
+            self.rotated = false;
+            self.keys = 0;
+            self.held_keys.shifted = false;
+            self.landed = 0;
+            observer.notify(Event::PieceSpawned(tetromino));
+            return self.apply_buffer(observer);
+        } else {
+            self.game_over = true;
+            observer.notify(Event::GameOver);
+        }
+        self
+    }
+
+    /// initial hold and rotation pressed during the delay before the block
+    /// that just spawned
+    fn apply_buffer(mut self, observer: &mut dyn Observer) -> Self {
+        let buffer = std::mem::take(&mut self.buffer);
+        if buffer.hold {
+            self = self.input_hold(observer);
+        }
+        match buffer.rotation {
+            Some(direction) => {
+                self.input_with(Input::Rotate(InputRotate::new(direction, 1)), observer)
+            }
+            None => self,
+        }
+    }
+
+    /// keys of `block` locking now, the search for the optimal ones is
+    /// skipped when no fewer keys could turn and shift it there
+    fn finesse(&self, block: &Block) -> finesse::Finesse {
+        let tetromino = block.block.tetromino();
+        let spawn = Block::spawn(tetromino, &self.board);
+        let mut shape = spawn.block;
+        let mut rotations = Vec::with_capacity(4);
+        let flip = self.ruleset.rotation == ruleset::RotationSystem::Flip;
+        for r in 0..4 {
+            if shape == block.block {
+                rotations.push(match r {
+                    2 if flip => 1,
+                    r => r.min(4 - r),
+                });
+            }
+            shape = shape.rotate(core::RotateDirection::Right);
+        }
+        let rotations = rotations.into_iter().min().unwrap_or(0);
+        // the stack can stop a DAS anywhere, a sideways key is all that is sure
+        let sideways = usize::from(block.position.x != spawn.position.x);
+        let least = rotations.max(sideways);
+        let optimal = match self.keys <= least {
+            true => Some(self.keys),
+            false => finesse::optimal(
+                &self.board,
+                tetromino,
+                &block.cells(),
+                self.ruleset.rotation,
+            ),
+        };
+        finesse::Finesse {
+            tetromino,
+            keys: self.keys,
+            optimal: optimal.unwrap_or(self.keys),
+        }
+    }
+
+    /// drop the falling block and lock it at once, without waiting for the
+    /// lock delay
+    ///
+    /// return self and removed row-count
+    pub fn hard_drop(self) -> (Self, usize) {
+        self.hard_drop_with(&mut ())
+    }
+
+    pub fn hard_drop_with(self, observer: &mut dyn Observer) -> (Self, usize) {
+        if self.block.is_none() {
+            return (self, 0);
+        }
+        let mut game = self.input_with(movegen::Step::Drop.into(), observer);
+        game.landed = game.ruleset.lock_delay;
+        game.deside_with(observer)
+    }
+
+    /// run out the delays after a lock and spawn the next block, for
+    /// drivers that place blocks without `Game::tick`
+    pub fn advance_to_spawn(self) -> Self {
+        self.advance_to_spawn_with(&mut ())
+    }
+
+    pub fn advance_to_spawn_with(mut self, observer: &mut dyn Observer) -> Self {
+        if self.game_over || self.block.is_some() {
+            return self;
+        }
+        while self.phase.is_waiting() {
+            self.phase.tick(&self.ruleset.delays);
+        }
+        self.spawn(observer)
     }
 
     /// return self and removed row-count
-    pub fn deside(mut self) -> (Self, usize) {
-        match self.block {
+    pub fn deside(self) -> (Self, usize) {
+        self.deside_with(&mut ())
+    }
+
+    pub fn deside_with(mut self, observer: &mut dyn Observer) -> (Self, usize) {
+        if self.game_over {
+            return (self, 0);
+        }
+
+        let mut locked = false;
+        let mut t_spin = None;
+        match self.block.take() {
+            None if self.phase.is_waiting() => {}
             None => {
-                self.block = Some(Block::new(core::Position::new(3, 0), core::Block::new()));
+                self = self.spawn(observer);
             }
             Some(block) => {
                 let direction = InputMoveDirection::Bottom;
                 let block = block.r#move(&direction);
                 if block.can_change(&self.board) {
                     self.block = Some(block);
+                    self.rotated = false;
+                    self.landed = 0;
+                } else if self.landed < self.ruleset.lock_delay {
+                    self.block = Some(block.reverse_move(&direction));
                 } else {
                     let block = block.reverse_move(&direction);
+                    observer.notify(Event::PieceLocked {
+                        tetromino: block.block.tetromino(),
+                        position: block.position.clone(),
+                    });
+                    let finesse = self.finesse(&block);
+                    self.finesse_faults += finesse.faults();
+                    observer.notify(Event::Finesse(finesse));
+                    t_spin = self.t_spin(&block);
                     self.board = self.board.set_block(&block.position, block.block);
-                    self.block = None;
+                    self.held = false;
+                    locked = true;
                 }
             }
         }
 
+        let rows = self.board.valid_rows();
         let (board, cnt) = self.board.remove_valid_rows();
         self.board = board;
+
+        if locked {
+            self.phase = timing::Phase::after(&self.ruleset.delays, cnt);
+        }
+        if locked && cnt == 0 {
+            self.combo = 0;
+        }
+        if let Some(kind) = ClearKind::new(cnt) {
+            let mut clear = Clear {
+                kind,
+                t_spin,
+                combo: self.combo,
+                back_to_back: false,
+                all_clear: self.board.is_empty(),
+            };
+            clear.back_to_back = clear.is_difficult() && self.back_to_back;
+            self.back_to_back = clear.is_difficult();
+            self.combo += 1;
+            observer.notify(Event::LinesCleared { rows, clear });
+
+            let level = self.level();
+            self.score += self.ruleset.score(cnt, level);
+            self.lines += cnt;
+            if self.level() > level {
+                observer.notify(Event::LevelUp(self.level()));
+            }
+        }
         (self, cnt)
     }
 
@@ -254,29 +921,181 @@ impl Game {
         self.board.size
     }
 
+    /// locked blocks, without the falling one
+    pub fn board(&self) -> &core::Board {
+        &self.board
+    }
+
+    /// tetromino of the falling block
+    pub fn current(&self) -> Option<core::Tetromino> {
+        self.block.as_ref().map(|block| block.block.tetromino())
+    }
+
+    /// upcoming tetrominos, filled when the first block spawns
+    pub fn next(&self) -> &[core::Tetromino] {
+        &self.next
+    }
+
+    pub fn hold(&self) -> Option<core::Tetromino> {
+        self.hold
+    }
+
+    /// total removed rows
+    pub fn lines(&self) -> usize {
+        self.lines
+    }
+
+    pub fn score(&self) -> usize {
+        self.score
+    }
+
+    /// consecutive clearing locks, 0 after a lock removing no row
+    pub fn combo(&self) -> usize {
+        self.combo
+    }
+
+    /// the last clear was a tetris or a T-spin
+    pub fn back_to_back(&self) -> bool {
+        self.back_to_back
+    }
+
+    /// T-spin of a T locked right after a rotation: 3 of the 4 corners
+    /// around its center are filled, walls and floor included
+    fn t_spin(&self, block: &Block) -> Option<TSpin> {
+        if !self.rotated || block.block.tetromino() != core::Tetromino::T {
+            return None;
+        }
+
+        const SIDES: [(isize, isize); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+        let cells = block.cells();
+        let has = |x: isize, y: isize| cells.iter().any(|p| p.x as isize == x && p.y as isize == y);
+        let (x, y) = cells
+            .iter()
+            .map(|p| (p.x as isize, p.y as isize))
+            .find(|(x, y)| SIDES.iter().filter(|(dx, dy)| has(x + dx, y + dy)).count() == 3)?;
+        // the side the T points to
+        let (dx, dy) = SIDES
+            .into_iter()
+            .find(|(dx, dy)| has(x + dx, y + dy) && !has(x - dx, y - dy))?;
+
+        let size = self.board.size;
+        let filled = |x: isize, y: isize| {
+            if x < 0 || x >= size.x as isize || y >= size.y as isize {
+                return true;
+            }
+            y >= 0 && self.board.state(&Position::new(x as usize, y as usize)) != core::State::Empty
+        };
+        let corners = [(1, 1), (1, -1), (-1, 1), (-1, -1)]
+            .iter()
+            .filter(|(cx, cy)| filled(x + cx, y + cy))
+            .count();
+        if corners < 3 {
+            return None;
+        }
+
+        let front = filled(x + dx + dy, y + dy + dx) && filled(x + dx - dy, y + dy - dx);
+        Some(if front { TSpin::Full } else { TSpin::Mini })
+    }
+
+    /// starts at 1 and goes up every 10 lines
+    pub fn level(&self) -> usize {
+        self.lines / 10 + 1
+    }
+
+    /// sideways keys and rotations of the falling block so far, a held
+    /// sideways key counts once however far it shifts
+    pub fn keys(&self) -> usize {
+        self.keys
+    }
+
+    /// keys beyond the optimal ones, over all locked blocks
+    pub fn finesse_faults(&self) -> usize {
+        self.finesse_faults
+    }
+
+    pub fn handling(&self) -> &handling::Handling {
+        &self.handling
+    }
+
+    pub fn ruleset(&self) -> &ruleset::Ruleset {
+        &self.ruleset
+    }
+
+    pub fn delays(&self) -> &timing::Delays {
+        &self.ruleset.delays
+    }
+
+    /// waiting or not for the next block
+    pub fn phase(&self) -> timing::Phase {
+        self.phase
+    }
+
+    /// soft drop is held, gravity is `Handling::soft_drop_factor` times faster
+    pub fn is_soft_dropping(&self) -> bool {
+        self.held_keys.soft_drop
+    }
+
+    /// hold is available for the falling block
+    pub fn can_hold(&self) -> bool {
+        self.ruleset.hold && self.block.is_some() && !self.held
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.game_over
+    }
+
+    /// state of the falling block only, ignoring the board
+    pub fn block_state(&self, p: Position) -> core::State {
+        match &self.block {
+            Some(block) => block.state(&p),
+            None => core::State::Empty,
+        }
+    }
+
+    /// where the falling block lands if dropped straight down
+    pub fn ghost_position(&self) -> Option<Position> {
+        self.ghost().map(|ghost| ghost.position)
+    }
+
+    fn ghost(&self) -> Option<Block> {
+        let mut ghost = self.block.clone()?;
+        loop {
+            let block = ghost.clone().r#move(&InputMoveDirection::Bottom);
+            if !block.can_change(&self.board) {
+                return Some(ghost);
+            }
+            ghost = block;
+        }
+    }
+
     pub fn state(&self, p: Position) -> core::State {
+        self.state_with_ghost(&p, &self.ghost())
+    }
+
+    fn state_with_ghost(&self, p: &Position, ghost: &Option<Block>) -> core::State {
         if let Some(block) = &self.block {
-            if let core::State::Block(t) = block.state(&p) {
+            if let core::State::Block(t) = block.state(p) {
                 return core::State::Block(t);
             }
         }
-        self.board.state(&p)
+
+        if let core::State::Empty = self.board.state(p) {
+            if let Some(ghost) = ghost {
+                if let core::State::Block(t) = ghost.state(p) {
+                    return core::State::Ghost(t);
+                }
+            }
+        }
+        self.board.state(p)
     }
 
     pub fn table(&self) -> Vec<Vec<core::State>> {
+        let ghost = self.ghost();
         let mut table = Vec::with_capacity(self.board.size.y);
         for y in 0..self.board.size.y {
             let mut row = Vec::with_capacity(self.board.size.x);
             for x in 0..self.board.size.x {
-                let p = core::Position::new(x, y);
-                if let Some(block) = &self.block {
-                    if let core::State::Block(t) = block.state(&p) {
-                        row.push(core::State::Block(t));
-                        continue;
-                    }
-                }
-
-                row.push(self.board.state(&p));
+                row.push(self.state_with_ghost(&core::Position::new(x, y), &ghost));
             }
             table.push(row);
         }
@@ -312,6 +1131,7 @@ mod game_tests {
         let game = game;
         let mut expect = Game::new();
         expect.block = Some(Block::new(core::Position::new(7, 0), core::Block::new_s()));
+        expect.keys = 1;
         let expect = expect;
         assert_eq!(
             game.input(Input::Move(InputMove::new(InputMoveDirection::Right, 10))),
@@ -337,6 +1157,8 @@ mod game_tests {
             core::Position::new(0, 0),
             core::Block::new_s().rotate(core::RotateDirection::Left),
         ));
+        expect.rotated = true;
+        expect.keys = 1;
         let expect = expect;
         assert_eq!(
             game.input(Input::Rotate(InputRotate::new(
@@ -346,6 +1168,446 @@ mod game_tests {
             expect,
         );
     }
+
+    #[test]
+    fn game_block_state() {
+        let mut game = Game::new();
+        game.block = Some(Block::new(core::Position::new(4, 0), core::Block::new_s()));
+        game.board = game
+            .board
+            .set_block(&Position::new(0, 18), core::Block::new_o());
+
+        assert_eq!(
+            game.block_state(Position::new(5, 0)),
+            State::Block(core::Tetromino::S)
+        );
+        assert_eq!(game.block_state(Position::new(4, 0)), State::Empty);
+        assert_eq!(game.block_state(Position::new(0, 18)), State::Empty);
+        assert_eq!(
+            game.state(Position::new(0, 18)),
+            State::Block(core::Tetromino::O)
+        );
+    }
+
+    #[test]
+    fn game_ghost() {
+        let mut game = Game::new();
+        assert_eq!(game.ghost_position(), None);
+
+        game.block = Some(Block::new(core::Position::new(4, 0), core::Block::new_s()));
+        game.board = game
+            .board
+            .set_block(&Position::new(4, 18), core::Block::new_o());
+        assert_eq!(game.ghost_position(), Some(Position::new(4, 16)));
+
+        assert_eq!(
+            game.state(Position::new(5, 16)),
+            State::Ghost(core::Tetromino::S)
+        );
+        assert_eq!(
+            game.state(Position::new(5, 18)),
+            State::Block(core::Tetromino::O)
+        );
+        assert_eq!(game.table()[17][4], State::Ghost(core::Tetromino::S));
+        assert_eq!(game.table()[16][4], State::Empty);
+    }
+
+    #[test]
+    fn game_hold() {
+        let mut game = Game::new();
+        game.block = Some(Block::new(core::Position::new(0, 0), core::Block::new_s()));
+        let game = game.input(Input::Hold);
+        assert_eq!(game.hold(), Some(core::Tetromino::S));
+        assert_eq!(game.next().len(), NEXT_SIZE);
+        assert!(game.block.is_some());
+
+        let block = game.block.clone();
+        let game = game.input(Input::Hold);
+        assert_eq!(game.hold(), Some(core::Tetromino::S));
+        assert_eq!(game.block, block);
+    }
+
+    #[test]
+    fn game_events() {
+        let mut events = Vec::new();
+
+        let mut game = Game::new();
+        game.block = Some(Block::new(core::Position::new(0, 0), core::Block::new_o()));
+        let game = game.input_with(
+            Input::Move(InputMove::new(InputMoveDirection::Left, 1)),
+            &mut events,
+        );
+        let game = game.input_with(
+            Input::Move(InputMove::new(InputMoveDirection::Bottom, 20)),
+            &mut events,
+        );
+        let game = game.input_with(
+            Input::Rotate(InputRotate::new(InputRotateDirection::Left, 1)),
+            &mut events,
+        );
+        assert_eq!(
+            events,
+            vec![
+                Event::PieceMoved {
+                    position: Position::new(0, 18)
+                },
+                Event::PieceRotated { kick: 0 },
+            ]
+        );
+
+        let mut game = game;
+        game.lines = 8;
+        game.board = game
+            .board
+            .set_block(&Position::new(2, 18), core::Block::new_o())
+            .set_block(&Position::new(4, 18), core::Block::new_o())
+            .set_block(&Position::new(6, 18), core::Block::new_o())
+            .set_block(&Position::new(8, 18), core::Block::new_o());
+
+        let mut events = Vec::new();
+        let (game, cnt) = game.deside_with(&mut events);
+        assert_eq!(cnt, 2);
+        assert_eq!(game.lines(), 10);
+        assert_eq!(game.score(), 300);
+        assert_eq!(
+            events,
+            vec![
+                Event::PieceLocked {
+                    tetromino: core::Tetromino::O,
+                    position: Position::new(0, 18)
+                },
+                Event::Finesse(finesse::Finesse {
+                    tetromino: core::Tetromino::O,
+                    keys: 1,
+                    optimal: 1,
+                }),
+                Event::LinesCleared {
+                    rows: vec![18, 19],
+                    clear: Clear {
+                        kind: ClearKind::Double,
+                        t_spin: None,
+                        combo: 0,
+                        back_to_back: false,
+                        all_clear: true,
+                    }
+                },
+                Event::LevelUp(2),
+            ]
+        );
+
+        let mut events = Vec::new();
+        let (game, _) = game.deside_with(&mut events);
+        let t = game.block.as_ref().unwrap().block.tetromino();
+        assert_eq!(events, vec![Event::PieceSpawned(t)]);
+
+        let mut events = Vec::new();
+        let game = game.input_with(Input::Hold, &mut events);
+        assert_eq!(events[0], Event::Hold(t));
+        assert_eq!(game.hold(), Some(t));
+    }
+
+    #[test]
+    fn game_over() {
+        let mut game = Game::new();
+        game.board = game
+            .board
+            .set_block(&Position::new(3, 0), core::Block::new_o())
+            .set_block(&Position::new(5, 0), core::Block::new_o());
+        let (game, _) = game.deside();
+        assert!(game.is_game_over());
+        assert_eq!(game.block, None);
+    }
+
+    #[test]
+    fn game_with_randomizer() {
+        let play = |seed| {
+            let mut game = Game::with_randomizer(randomizer::Randomizer::Bag, seed);
+            let mut pieces = Vec::new();
+            for _ in 0..14 {
+                game = game.deside().0;
+                pieces.push(game.current().unwrap());
+                game.block = None;
+            }
+            pieces
+        };
+        assert_eq!(play(7), play(7));
+        assert_ne!(play(7), play(8));
+    }
+
+    #[test]
+    fn game_handling() {
+        use handling::{Action, Handling};
+        let x = |game: &Game| game.block.as_ref().map(|block| block.position.x);
+        let game = Game::with_sequence(&[core::Tetromino::O; 3])
+            .with_handling(Handling {
+                das: 2,
+                arr: 0,
+                soft_drop_factor: 0,
+            })
+            .deside()
+            .0;
+
+        let game = game.input(Input::KeyDown(Action::Left));
+        assert_eq!(x(&game), Some(2));
+        let game = game.tick();
+        assert_eq!(x(&game), Some(2));
+        let game = game.tick();
+        assert_eq!(x(&game), Some(0));
+        let game = game.input(Input::KeyUp(Action::Left));
+
+        // hard drop locks, DAS charges before the next block spawns
+        let game = game.input(Input::KeyDown(Action::HardDrop));
+        assert_eq!(game.current(), None);
+        let game = game.input(Input::KeyDown(Action::Right)).tick().tick();
+        let game = game.deside().0.tick();
+        assert_eq!(x(&game), Some(8));
+
+        let game = game.input(Input::KeyDown(Action::SoftDrop));
+        assert!(game.is_soft_dropping());
+        let game = game.tick();
+        assert_eq!(game.block.as_ref().unwrap().position.y, 18);
+        let game = game.input(Input::KeyUp(Action::SoftDrop));
+        assert!(!game.is_soft_dropping());
+    }
+
+    #[test]
+    fn game_delays() {
+        use crate::core::Tetromino::{I, O, T};
+        use handling::Action;
+        let game = Game::with_sequence(&[O, T, I])
+            .with_delays(timing::Delays {
+                are: 2,
+                line_clear: 0,
+            })
+            .deside()
+            .0
+            .input(Input::KeyDown(Action::HardDrop));
+        assert_eq!(game.phase(), timing::Phase::Entry(2));
+
+        // gravity waits, rotation and hold are kept for the next block
+        let game = game
+            .deside()
+            .0
+            .input(Input::Rotate(InputRotate::new(
+                InputRotateDirection::Right,
+                1,
+            )))
+            .input(Input::Hold)
+            .tick();
+        assert_eq!(game.current(), None);
+
+        let mut events = Vec::new();
+        let game = game.tick_with(&mut events);
+        assert_eq!(game.phase(), timing::Phase::Falling);
+        assert_eq!(game.current(), Some(I));
+        assert_eq!(game.hold(), Some(T));
+        assert_eq!(
+            game.block.as_ref().unwrap().block,
+            core::Block::new_i().rotate(core::RotateDirection::Right)
+        );
+        assert_eq!(
+            events,
+            vec![
+                Event::PieceSpawned(T),
+                Event::Hold(T),
+                Event::PieceSpawned(I),
+                Event::PieceRotated { kick: 0 },
+            ]
+        );
+    }
+
+    #[test]
+    fn game_ruleset() {
+        let ruleset = ruleset::Ruleset {
+            width: 6,
+            height: 8,
+            rotation: ruleset::RotationSystem::Simple,
+            lock_delay: 3,
+            preview: 1,
+            hold: false,
+            ..ruleset::Ruleset::classic()
+        };
+        let game = Game::with_ruleset(ruleset, Some(1)).deside().0;
+        assert_eq!(game.board().size.x, 6);
+        assert_eq!(game.next().len(), 1);
+        assert_eq!(game.block.as_ref().unwrap().position.x, 1);
+        assert!(!game.can_hold());
+
+        let block = game.block.clone();
+        let game = game.input(Input::Rotate(InputRotate::new(
+            InputRotateDirection::Flip,
+            1,
+        )));
+        assert_eq!(game.block, block);
+
+        // the landed block waits for the lock delay, gravity or not
+        let game = game
+            .input(movegen::Step::Drop.into())
+            .deside()
+            .0
+            .tick()
+            .tick();
+        assert!(game.block.is_some());
+        let mut events = Vec::new();
+        let game = game.tick_with(&mut events);
+        assert!(game.block.is_none());
+        assert!(matches!(events[0], Event::PieceLocked { .. }));
+    }
+
+    #[test]
+    fn game_flip() {
+        let flip = || Input::Rotate(InputRotate::new(InputRotateDirection::Flip, 1));
+        let mut game = Game::new();
+        game.block = Some(Block::new(core::Position::new(3, 0), core::Block::new_t()));
+        let flipped = core::Block::new_t()
+            .rotate(core::RotateDirection::Right)
+            .rotate(core::RotateDirection::Right);
+
+        let mut events = Vec::new();
+        let game = game.input_with(flip(), &mut events);
+        let block = game.block.as_ref().unwrap();
+        assert_eq!(block.block, flipped);
+        assert_eq!(block.position, core::Position::new(3, 0));
+        assert_eq!(game.keys(), 1);
+        assert_eq!(events, vec![Event::PieceRotated { kick: 0 }]);
+
+        // kicked right along the floor, 2 to 0 never kicks up
+        let mut game = Game::new();
+        game.block = Some(Block::new(core::Position::new(0, 18), core::Block::new_t()));
+        game.board = game
+            .board
+            .set_state(&Position::new(0, 19), core::State::Garbage);
+        let mut events = Vec::new();
+        let game = game.input_with(flip(), &mut events);
+        let block = game.block.as_ref().unwrap();
+        assert_eq!(block.block, flipped);
+        assert_eq!(block.position, core::Position::new(1, 18));
+        assert_eq!(events, vec![Event::PieceRotated { kick: 5 }]);
+    }
+
+    #[test]
+    fn game_flip_kicks() {
+        // . []          [] .
+        // [][]  flips to [][]
+        // . []          [] .
+        // in place it would cover the garbage, pointing left (L) the first
+        // kick is to the left
+        let mut game = Game::new();
+        game.block = Some(Block::new(
+            core::Position::new(3, 5),
+            core::Block::new_t().rotate(core::RotateDirection::Right),
+        ));
+        game.board = game
+            .board
+            .set_state(&Position::new(3, 5), core::State::Garbage);
+        assert_eq!(game.block.as_ref().unwrap().srs_state(), 3);
+
+        let mut events = Vec::new();
+        let game = game.input_with(
+            Input::Rotate(InputRotate::new(InputRotateDirection::Flip, 1)),
+            &mut events,
+        );
+        let block = game.block.as_ref().unwrap();
+        assert_eq!(block.position, core::Position::new(2, 5));
+        assert_eq!(block.srs_state(), 1);
+        assert_eq!(events, vec![Event::PieceRotated { kick: 1 }]);
+
+        // [][][]          . [] .
+        // . [] .  flips to [][][]
+        // the T spawns pointing down (2), its first kick is down
+        let mut game = Game::new();
+        game.block = Some(Block::new(core::Position::new(3, 5), core::Block::new_t()));
+        game.board = game
+            .board
+            .set_state(&Position::new(3, 6), core::State::Garbage);
+        assert_eq!(game.block.as_ref().unwrap().srs_state(), 2);
+
+        let game = game.input(Input::Rotate(InputRotate::new(
+            InputRotateDirection::Flip,
+            1,
+        )));
+        let block = game.block.as_ref().unwrap();
+        assert_eq!(block.position, core::Position::new(3, 6));
+        assert_eq!(block.srs_state(), 0);
+    }
+
+    #[test]
+    fn game_with_sequence() {
+        let game = Game::with_sequence(&[core::Tetromino::T, core::Tetromino::I])
+            .deside()
+            .0;
+        assert_eq!(game.current(), Some(core::Tetromino::T));
+        assert_eq!(game.next(), &[core::Tetromino::I]);
+
+        let mut game = game.input(Input::Hold);
+        assert_eq!(game.hold(), Some(core::Tetromino::T));
+        game.block = None;
+        let game = game.deside().0;
+        assert_eq!(game.current(), None);
+        assert!(!game.is_game_over());
+
+        let game = Game::with_sequence(&[core::Tetromino::T])
+            .without_hold()
+            .deside()
+            .0;
+        assert!(!game.can_hold());
+        assert_eq!(game.input(Input::Hold).hold(), None);
+    }
+
+    #[test]
+    fn game_t_spin() {
+        // . . .[] . . . . . .
+        // [][][] . . .[][][][]
+        // [][][][] .[][][][][]
+        let mut board = core::Board::new().set_state(
+            &Position::new(3, 17),
+            core::State::Block(core::Tetromino::O),
+        );
+        for x in (0..10).filter(|x| !(3..6).contains(x)) {
+            board = board.set_state(
+                &Position::new(x, 18),
+                core::State::Block(core::Tetromino::O),
+            );
+        }
+        for x in (0..10).filter(|x| *x != 4) {
+            board = board.set_state(
+                &Position::new(x, 19),
+                core::State::Block(core::Tetromino::O),
+            );
+        }
+
+        let mut game = Game::new();
+        game.board = board;
+        game.block = Some(Block::new(Position::new(3, 18), core::Block::new_t()));
+        game.rotated = true;
+        game.combo = 2;
+        game.back_to_back = true;
+
+        let mut events = Vec::new();
+        let (game, cnt) = game.clone().deside_with(&mut events);
+        assert_eq!(cnt, 2);
+        assert_eq!(
+            events[2],
+            Event::LinesCleared {
+                rows: vec![18, 19],
+                clear: Clear {
+                    kind: ClearKind::Double,
+                    t_spin: Some(TSpin::Full),
+                    combo: 2,
+                    back_to_back: true,
+                    all_clear: false,
+                }
+            }
+        );
+        assert_eq!(game.combo(), 3);
+        assert!(game.back_to_back());
+
+        let mut game = game;
+        game.block = Some(Block::new(Position::new(0, 0), core::Block::new_o()));
+        let game = game.input(movegen::Step::Drop.into()).deside().0;
+        assert_eq!(game.combo(), 0);
+        assert!(game.back_to_back());
+    }
 }
 
 impl fmt::Display for Game {
@@ -0,0 +1,625 @@
+use super::{core, handling, stats, Event, Game, HoleStrategy, Input, Observer};
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// what a mode looks at to decide whether its goal is reached
+#[derive(Debug, PartialEq, Clone)]
+pub struct Progress {
+    /// engine clock, advanced by `Session::update`
+    pub elapsed: Duration,
+    pub lines: usize,
+    pub score: usize,
+    pub level: usize,
+    /// locked blocks
+    pub pieces: usize,
+}
+
+impl Progress {
+    /// pieces per second
+    pub fn pps(&self) -> f64 {
+        match self.elapsed.as_secs_f64() {
+            secs if secs > 0.0 => self.pieces as f64 / secs,
+            _ => 0.0,
+        }
+    }
+}
+
+/// what sessions of a mode are ranked by
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Metric {
+    /// lower is better
+    Time,
+    Score,
+}
+
+/// rules of a session on top of `Game`
+pub trait GameMode {
+    fn name(&self) -> String;
+
+    fn metric(&self) -> Metric {
+        Metric::Score
+    }
+
+    /// the clock stops here and the session ends
+    fn time_limit(&self) -> Option<Duration> {
+        None
+    }
+
+    /// the game a session starts from
+    fn setup(&mut self, game: Game) -> Game {
+        game
+    }
+
+    /// called after every change of the game with its events, to add
+    /// garbage for example
+    fn update(&mut self, game: Game, _events: &[Event]) -> Game {
+        game
+    }
+
+    fn gravity(&self, game: &Game) -> Duration {
+        game.ruleset().gravity(game.level())
+    }
+
+    /// the goal is reached and the session ends
+    fn is_finished(&self, progress: &Progress) -> bool;
+
+    /// the goal can no longer be reached and the session ends
+    fn is_failed(&self, _progress: &Progress) -> bool {
+        false
+    }
+}
+
+/// open-ended play until the game is over
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Endless;
+
+impl GameMode for Endless {
+    fn name(&self) -> String {
+        "Endless".to_string()
+    }
+
+    fn is_finished(&self, _: &Progress) -> bool {
+        false
+    }
+}
+
+/// clear `lines` rows as fast as possible, at level 1 gravity
+#[derive(Debug, PartialEq, Clone)]
+pub struct Sprint {
+    pub lines: usize,
+}
+
+impl Sprint {
+    pub const GOALS: [usize; 3] = [20, 40, 100];
+
+    pub fn new(lines: usize) -> Self {
+        Self { lines }
+    }
+}
+
+impl Default for Sprint {
+    fn default() -> Self {
+        Self::new(40)
+    }
+}
+
+impl GameMode for Sprint {
+    fn name(&self) -> String {
+        format!("Sprint {}L", self.lines)
+    }
+
+    fn metric(&self) -> Metric {
+        Metric::Time
+    }
+
+    fn gravity(&self, game: &Game) -> Duration {
+        game.ruleset().gravity(1)
+    }
+
+    fn is_finished(&self, progress: &Progress) -> bool {
+        progress.lines >= self.lines
+    }
+}
+
+/// score as much as possible before the time runs out, at level 1 gravity
+#[derive(Debug, PartialEq, Clone)]
+pub struct Ultra {
+    pub duration: Duration,
+}
+
+impl Ultra {
+    pub const DURATIONS: [Duration; 2] = [Duration::from_secs(120), Duration::from_secs(180)];
+
+    pub fn new(duration: Duration) -> Self {
+        Self { duration }
+    }
+}
+
+impl Default for Ultra {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(120))
+    }
+}
+
+impl GameMode for Ultra {
+    fn name(&self) -> String {
+        format!("Ultra {}s", self.duration.as_secs())
+    }
+
+    fn time_limit(&self) -> Option<Duration> {
+        Some(self.duration)
+    }
+
+    fn gravity(&self, game: &Game) -> Duration {
+        game.ruleset().gravity(1)
+    }
+
+    fn is_finished(&self, progress: &Progress) -> bool {
+        progress.elapsed >= self.duration
+    }
+}
+
+/// clear `lines` rows, with gravity speeding up every level;
+/// reaching the goal wins
+#[derive(Debug, PartialEq, Clone)]
+pub struct Marathon {
+    pub lines: usize,
+}
+
+impl Marathon {
+    pub const GOALS: [usize; 2] = [150, 200];
+
+    pub fn new(lines: usize) -> Self {
+        Self { lines }
+    }
+}
+
+impl Default for Marathon {
+    fn default() -> Self {
+        Self::new(150)
+    }
+}
+
+impl GameMode for Marathon {
+    fn name(&self) -> String {
+        format!("Marathon {}L", self.lines)
+    }
+
+    fn is_finished(&self, progress: &Progress) -> bool {
+        progress.lines >= self.lines
+    }
+}
+
+/// clear `lines` garbage rows, with up to `height` of them on the board
+/// and more pushed in as they are cleared
+#[derive(Debug, PartialEq, Clone)]
+pub struct Dig {
+    pub lines: usize,
+    pub height: usize,
+    /// holes in each row, 1 for clean cheese and more for a denser dig
+    pub holes: usize,
+    pub strategy: HoleStrategy,
+    /// garbage rows cleared so far
+    cleared: usize,
+    /// garbage rows on the board after the last update
+    remaining: usize,
+}
+
+impl Dig {
+    pub const GOALS: [usize; 3] = [10, 18, 100];
+
+    pub fn new(lines: usize) -> Self {
+        Self {
+            lines,
+            height: 10,
+            holes: 1,
+            strategy: HoleStrategy::Random,
+            cleared: 0,
+            remaining: 0,
+        }
+    }
+
+    /// garbage rows cleared so far
+    pub fn cleared(&self) -> usize {
+        self.cleared
+    }
+
+    /// rows holding any garbage
+    fn garbage_rows(game: &Game) -> usize {
+        let size = game.size();
+        (0..size.y)
+            .filter(|y| {
+                (0..size.x)
+                    .any(|x| game.board.state(&core::Position::new(x, *y)) == core::State::Garbage)
+            })
+            .count()
+    }
+
+    /// push `rows` garbage rows with the configured holes
+    fn push(&self, mut game: Game, rows: usize) -> Game {
+        let size = game.size();
+        let extra = (self.holes.max(1) - 1).min(size.x.saturating_sub(2));
+        let board = game.board.clone();
+        let (board, top_out) = game.generator.with_rng(|rng| {
+            let (mut board, top_out) = board.push_garbage(rows, &self.strategy, rng);
+            for y in size.y - rows.min(size.y)..size.y {
+                let filled: Vec<_> = (0..size.x)
+                    .map(|x| core::Position::new(x, y))
+                    .filter(|p| board.state(p) == core::State::Garbage)
+                    .collect();
+                for p in filled.choose_multiple(rng, extra) {
+                    board = board.set_state(p, core::State::Empty);
+                }
+            }
+            (board, top_out)
+        });
+
+        game.board = board;
+        let blocked = game
+            .block
+            .as_ref()
+            .is_some_and(|block| !block.can_change(&game.board));
+        if top_out || blocked {
+            game.game_over = true;
+            game.block = None;
+        }
+        game
+    }
+}
+
+impl Default for Dig {
+    fn default() -> Self {
+        Self::new(18)
+    }
+}
+
+impl GameMode for Dig {
+    fn name(&self) -> String {
+        format!("Dig {}L", self.lines)
+    }
+
+    fn metric(&self) -> Metric {
+        Metric::Time
+    }
+
+    fn setup(&mut self, game: Game) -> Game {
+        self.cleared = 0;
+        self.remaining = 0;
+        self.update(game, &[])
+    }
+
+    /// count the garbage rows cleared and top up the rest
+    fn update(&mut self, game: Game, _: &[Event]) -> Game {
+        let remaining = Self::garbage_rows(&game);
+        self.cleared += self.remaining.saturating_sub(remaining);
+
+        let wanted = self.height.min(self.lines.saturating_sub(self.cleared));
+        let rows = wanted.saturating_sub(remaining);
+        self.remaining = remaining + rows;
+        match rows {
+            0 => game,
+            rows => self.push(game, rows),
+        }
+    }
+
+    fn gravity(&self, game: &Game) -> Duration {
+        game.ruleset().gravity(1)
+    }
+
+    fn is_finished(&self, _: &Progress) -> bool {
+        self.cleared >= self.lines
+    }
+}
+
+/// final state of a session
+#[derive(Debug, PartialEq, Clone)]
+pub struct Outcome {
+    pub mode: String,
+    pub metric: Metric,
+    /// the goal was reached, rather than failed or the game being over
+    pub finished: bool,
+    pub progress: Progress,
+}
+
+/// a game played under a mode, with its clock
+pub struct Session {
+    mode: Box<dyn GameMode>,
+    game: Game,
+    elapsed: Duration,
+    /// time since the last gravity tick
+    falling: Duration,
+    /// time since the last frame
+    frame: Duration,
+    stats: stats::Stats,
+    finished: bool,
+    failed: bool,
+}
+
+impl Session {
+    pub fn new(mode: Box<dyn GameMode>) -> Self {
+        Self::with_game(mode, Game::new())
+    }
+
+    /// start from `game`, for example a seeded one
+    pub fn with_game(mut mode: Box<dyn GameMode>, game: Game) -> Self {
+        let game = mode.setup(game);
+        Self {
+            mode,
+            game,
+            elapsed: Duration::ZERO,
+            falling: Duration::ZERO,
+            frame: Duration::ZERO,
+            stats: stats::Stats::default(),
+            finished: false,
+            failed: false,
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn mode(&self) -> &dyn GameMode {
+        self.mode.as_ref()
+    }
+
+    pub fn stats(&self) -> &stats::Stats {
+        &self.stats
+    }
+
+    pub fn progress(&self) -> Progress {
+        Progress {
+            elapsed: self.elapsed,
+            lines: self.game.lines(),
+            score: self.game.score(),
+            level: self.game.level(),
+            pieces: self.stats.pieces,
+        }
+    }
+
+    /// the goal is reached
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// the goal can no longer be reached
+    pub fn is_failed(&self) -> bool {
+        self.failed
+    }
+
+    /// finished, failed or game over, the clock is stopped
+    pub fn is_over(&self) -> bool {
+        self.finished || self.failed || self.game.is_game_over()
+    }
+
+    /// result once the session is over
+    pub fn outcome(&self) -> Option<Outcome> {
+        if !self.is_over() {
+            return None;
+        }
+        Some(Outcome {
+            mode: self.mode.name(),
+            metric: self.mode.metric(),
+            finished: self.finished,
+            progress: self.progress(),
+        })
+    }
+
+    /// update the game, `f` passes the observer on so locks are counted
+    ///
+    /// return removed row-count
+    pub fn step(&mut self, f: impl FnOnce(Game, &mut dyn Observer) -> Game) -> usize {
+        if self.is_over() {
+            return 0;
+        }
+
+        let lines = self.game.lines();
+        let mut events = Vec::new();
+        let game = f(self.game.clone(), &mut events);
+        self.game = self.mode.update(game, &events);
+        for event in events {
+            self.stats.notify(event);
+        }
+        self.check();
+        self.game.lines() - lines
+    }
+
+    pub fn input(&mut self, input: Input) {
+        self.step(|game, observer| game.input_with(input, observer));
+    }
+
+    /// gravity tick, return removed row-count
+    pub fn deside(&mut self) -> usize {
+        self.step(|game, observer| game.deside_with(observer).0)
+    }
+
+    /// advance the clock by `delta`, running the frames of held keys and
+    /// applying gravity as it comes due, up to the mode's time limit
+    ///
+    /// return removed row-count
+    pub fn update(&mut self, delta: Duration) -> usize {
+        if self.is_over() {
+            return 0;
+        }
+
+        let delta = match self.mode.time_limit() {
+            Some(limit) => delta.min(limit.saturating_sub(self.elapsed)),
+            None => delta,
+        };
+
+        self.elapsed += delta;
+        self.stats.duration = self.elapsed;
+        self.falling += delta;
+        self.frame += delta;
+        let mut lines = 0;
+        while self.frame >= handling::FRAME && !self.is_over() {
+            self.frame -= handling::FRAME;
+            lines += self.step(|game, observer| game.tick_with(observer));
+        }
+        loop {
+            let mut gravity = self.mode.gravity(&self.game);
+            if self.game.is_soft_dropping() {
+                gravity /= self.game.handling().soft_drop_factor.max(1) as u32;
+            }
+            if self.falling < gravity || self.is_over() {
+                break;
+            }
+            self.falling -= gravity;
+            lines += self.deside();
+        }
+        if !self.is_over() {
+            self.check();
+        }
+        lines
+    }
+
+    fn check(&mut self) {
+        let progress = self.progress();
+        self.finished = self.mode.is_finished(&progress);
+        self.failed = !self.finished && self.mode.is_failed(&progress);
+    }
+}
+
+#[cfg(test)]
+mod mode_tests {
+    use super::*;
+    use crate::{core, Block};
+
+    #[test]
+    fn mode_gravity() {
+        let mut game = Game::new();
+        game.lines = 20;
+        assert_eq!(Endless.gravity(&game), Duration::from_millis(850));
+        assert_eq!(Sprint::default().gravity(&game), Duration::from_secs(1));
+
+        let mut game = Game::with_ruleset(crate::ruleset::Ruleset::nes(), None);
+        game.lines = 20;
+        assert_eq!(Endless.gravity(&game), Duration::from_millis(632));
+        assert_eq!(Sprint::default().gravity(&game), Duration::from_millis(799));
+    }
+
+    #[test]
+    fn mode_session_clock() {
+        // the first block spawns on the first frame, not the first gravity tick
+        let mut session = Session::new(Box::new(Endless));
+        session.update(Duration::from_millis(10));
+        assert_eq!(session.game().current(), None);
+        session.update(Duration::from_millis(10));
+        assert!(session.game().current().is_some());
+        session.update(Duration::from_millis(980));
+
+        session.update(Duration::from_secs(30));
+        assert_eq!(session.progress().elapsed, Duration::from_millis(31_000));
+        assert!(session.progress().pieces > 0);
+        assert_eq!(session.outcome(), None);
+
+        let stats = session.stats();
+        assert_eq!(stats.pieces, session.progress().pieces);
+        assert_eq!(stats.duration, Duration::from_secs(31));
+        assert_eq!(stats.distribution.iter().sum::<usize>(), stats.pieces);
+    }
+
+    #[test]
+    fn mode_sprint() {
+        // [][][][][][][][] . .
+        // [][][][][][][][] . .
+        let mut game = Game::new();
+        for x in 0..8 {
+            for y in 18..20 {
+                game.board = game.board.set_state(
+                    &core::Position::new(x, y),
+                    core::State::Block(core::Tetromino::O),
+                );
+            }
+        }
+        game.block = Some(Block::new(core::Position::new(8, 0), core::Block::new_o()));
+
+        let mut session = Session::with_game(Box::new(Sprint::new(2)), game);
+        session.update(Duration::from_millis(2_500));
+        session.input(crate::movegen::Step::Drop.into());
+        assert_eq!(session.deside(), 2);
+        assert!(session.is_finished());
+
+        let outcome = session.outcome().unwrap();
+        assert_eq!(outcome.mode, "Sprint 2L");
+        assert_eq!(outcome.metric, Metric::Time);
+        assert!(outcome.finished);
+        assert_eq!(outcome.progress.elapsed, Duration::from_millis(2_500));
+        assert_eq!(outcome.progress.pieces, 1);
+        assert_eq!(outcome.progress.pps(), 0.4);
+
+        // the clock stops
+        session.update(Duration::from_secs(1));
+        assert_eq!(session.progress().elapsed, Duration::from_millis(2_500));
+    }
+
+    #[test]
+    fn mode_ultra() {
+        let mut session = Session::new(Box::new(Ultra::new(Duration::from_secs(5))));
+        session.update(Duration::from_secs(4));
+        assert!(!session.is_over());
+        session.update(Duration::from_secs(5));
+        assert!(session.is_finished());
+
+        let outcome = session.outcome().unwrap();
+        assert_eq!(outcome.mode, "Ultra 5s");
+        assert_eq!(outcome.metric, Metric::Score);
+        assert_eq!(outcome.progress.elapsed, Duration::from_secs(5));
+    }
+
+    #[test]
+    fn mode_marathon() {
+        let marathon = Marathon::default();
+        let game = Game::new();
+        assert_eq!(marathon.gravity(&game), Duration::from_secs(1));
+
+        let progress = |lines| Progress {
+            elapsed: Duration::ZERO,
+            lines,
+            score: 0,
+            level: lines / 10 + 1,
+            pieces: 0,
+        };
+        assert!(!marathon.is_finished(&progress(149)));
+        assert!(marathon.is_finished(&progress(150)));
+    }
+
+    #[test]
+    fn mode_dig() {
+        let mut dig = Dig::new(6);
+        dig.height = 4;
+        dig.holes = 2;
+        let mut session = Session::with_game(
+            Box::new(dig),
+            Game::with_randomizer(crate::randomizer::Randomizer::Bag, 1),
+        );
+        let board = session.game().board();
+        for y in 0..20 {
+            let empty = (0..10)
+                .filter(|x| board.state(&core::Position::new(*x, y)) == core::State::Empty)
+                .count();
+            assert_eq!(empty, if y < 16 { 10 } else { 2 });
+        }
+
+        // clearing the top garbage row pushes in another one
+        let mut game = session.game().clone();
+        for x in 0..10 {
+            game.board = game
+                .board
+                .set_state(&core::Position::new(x, 16), core::State::Garbage);
+        }
+        game.block = Some(Block::new(core::Position::new(0, 0), core::Block::new_o()));
+        session.step(|_, _| game);
+        session.deside();
+        assert_eq!(Dig::garbage_rows(session.game()), 4);
+
+        let bot = crate::bot::Bot::default();
+        while !session.is_over() {
+            session.step(|game, observer| bot.play_with(game, observer).0);
+        }
+        assert!(session.is_finished());
+        assert_eq!(Dig::garbage_rows(session.game()), 0);
+    }
+}
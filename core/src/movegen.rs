@@ -0,0 +1,278 @@
+use super::{
+    core, ruleset, Block, Input, InputMove, InputMoveDirection, InputRotate, InputRotateDirection,
+};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// single input applied to the falling block
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Step {
+    MoveLeft,
+    MoveRight,
+    /// left until the wall or the stack, a held key with DAS
+    DasLeft,
+    /// right until the wall or the stack, a held key with DAS
+    DasRight,
+    /// one row down
+    MoveBottom,
+    /// down until the block lands, without locking it
+    Drop,
+    RotateLeft,
+    RotateRight,
+    /// 180 degrees, with `ruleset::RotationSystem::Flip` only
+    RotateFlip,
+}
+
+impl Step {
+    const ALL: [Step; 9] = [
+        Step::MoveLeft,
+        Step::MoveRight,
+        Step::DasLeft,
+        Step::DasRight,
+        Step::Drop,
+        Step::MoveBottom,
+        Step::RotateLeft,
+        Step::RotateRight,
+        Step::RotateFlip,
+    ];
+
+    /// steps turning by `system`, without 180s for
+    /// `ruleset::RotationSystem::Simple`
+    pub(crate) fn allowed(system: ruleset::RotationSystem) -> Vec<Step> {
+        Self::ALL
+            .into_iter()
+            .filter(|step| *step != Step::RotateFlip || system == ruleset::RotationSystem::Flip)
+            .collect()
+    }
+
+    /// return the moved block and its rotation
+    pub(crate) fn apply(
+        &self,
+        block: &Block,
+        rotation: usize,
+        board: &core::Board,
+    ) -> Option<(Block, usize)> {
+        let next = match self {
+            Step::MoveLeft => (block.try_move(&InputMoveDirection::Left, board)?, rotation),
+            Step::MoveRight => (block.try_move(&InputMoveDirection::Right, board)?, rotation),
+            Step::MoveBottom => (
+                block.try_move(&InputMoveDirection::Bottom, board)?,
+                rotation,
+            ),
+            Step::DasLeft => (
+                Self::slide(block, &InputMoveDirection::Left, board)?,
+                rotation,
+            ),
+            Step::DasRight => (
+                Self::slide(block, &InputMoveDirection::Right, board)?,
+                rotation,
+            ),
+            Step::Drop => (
+                Self::slide(block, &InputMoveDirection::Bottom, board)?,
+                rotation,
+            ),
+            Step::RotateLeft => (
+                block.try_rotate(&InputRotateDirection::Left, board)?.0,
+                (rotation + 3) % 4,
+            ),
+            Step::RotateRight => (
+                block.try_rotate(&InputRotateDirection::Right, board)?.0,
+                (rotation + 1) % 4,
+            ),
+            Step::RotateFlip => (
+                block.try_rotate(&InputRotateDirection::Flip, board)?.0,
+                (rotation + 2) % 4,
+            ),
+        };
+        Some(next)
+    }
+
+    /// `direction` until the block is stopped, `None` if it cannot move
+    fn slide(block: &Block, direction: &InputMoveDirection, board: &core::Board) -> Option<Block> {
+        let mut block = block.try_move(direction, board)?;
+        while let Some(next) = block.try_move(direction, board) {
+            block = next;
+        }
+        Some(block)
+    }
+}
+
+impl From<Step> for Input {
+    fn from(step: Step) -> Self {
+        match step {
+            Step::MoveLeft => Input::Move(InputMove::new(InputMoveDirection::Left, 1)),
+            Step::MoveRight => Input::Move(InputMove::new(InputMoveDirection::Right, 1)),
+            Step::DasLeft => Input::Move(InputMove::new(InputMoveDirection::Left, usize::MAX)),
+            Step::DasRight => Input::Move(InputMove::new(InputMoveDirection::Right, usize::MAX)),
+            Step::MoveBottom => Input::Move(InputMove::new(InputMoveDirection::Bottom, 1)),
+            Step::Drop => Input::Move(InputMove::new(InputMoveDirection::Bottom, usize::MAX)),
+            Step::RotateLeft => Input::Rotate(InputRotate::new(InputRotateDirection::Left, 1)),
+            Step::RotateRight => Input::Rotate(InputRotate::new(InputRotateDirection::Right, 1)),
+            Step::RotateFlip => Input::Rotate(InputRotate::new(InputRotateDirection::Flip, 1)),
+        }
+    }
+}
+
+/// final resting place of a block
+#[derive(Debug, PartialEq, Clone)]
+pub struct Placement {
+    pub tetromino: core::Tetromino,
+    /// top-left of the rotated block
+    pub position: core::Position,
+    /// right rotations from the spawn orientation, 0 to 3
+    pub rotation: usize,
+    /// shortest inputs from spawn, the block locks after the last one
+    pub steps: Vec<Step>,
+}
+
+impl Placement {
+    fn block(&self) -> Block {
+        let mut block = core::Block::from(self.tetromino);
+        for _ in 0..self.rotation {
+            block = block.rotate(core::RotateDirection::Right);
+        }
+        Block::new(self.position.clone(), block)
+    }
+
+    /// cells covered by the block, from top-left
+    pub fn cells(&self) -> Vec<core::Position> {
+        self.block().cells()
+    }
+
+    /// return the board with the block locked, before rows are removed
+    pub fn lock(&self, board: core::Board) -> core::Board {
+        let block = self.block();
+        board.set_block(&block.position, block.block)
+    }
+}
+
+/// every place the tetromino can lock from its spawn, following the
+/// movement and rotation rules of `Game`
+///
+/// placements covering the same cells are listed once, with the shortest
+/// steps, so symmetric orientations of O, I, S and Z are not repeated
+pub fn placements(board: &core::Board, tetromino: core::Tetromino) -> Vec<Placement> {
+    placements_with(board, tetromino, ruleset::RotationSystem::Flip)
+}
+
+/// `placements` turning by `system`, without 180s for
+/// `ruleset::RotationSystem::Simple`
+pub fn placements_with(
+    board: &core::Board,
+    tetromino: core::Tetromino,
+    system: ruleset::RotationSystem,
+) -> Vec<Placement> {
+    let allowed = Step::allowed(system);
+    let spawn = Block::spawn(tetromino, board);
+    if !spawn.can_change(board) {
+        return Vec::new();
+    }
+
+    let mut visited = HashSet::new();
+    let mut landings = HashMap::new();
+    let mut found = HashSet::new();
+    let mut placements = Vec::new();
+    let mut queue = VecDeque::new();
+    visited.insert((spawn.position.clone(), 0));
+    queue.push_back((spawn, 0, Vec::new()));
+
+    while let Some((block, rotation, steps)) = queue.pop_front() {
+        if block.try_move(&InputMoveDirection::Bottom, board).is_none() {
+            let mut cells = block.cells();
+            cells.sort_by_key(|p| (p.y, p.x));
+            if found.insert(cells) {
+                placements.push(Placement {
+                    tetromino,
+                    position: block.position.clone(),
+                    rotation,
+                    steps: steps.clone(),
+                });
+            }
+        }
+
+        for step in allowed.iter().copied() {
+            let next = match step {
+                Step::Drop => landing(&block, rotation, board, &mut landings),
+                _ => step.apply(&block, rotation, board),
+            };
+            if let Some((next, rotation)) = next {
+                if visited.insert((next.position.clone(), rotation)) {
+                    let mut steps = steps.clone();
+                    steps.push(step);
+                    queue.push_back((next, rotation, steps));
+                }
+            }
+        }
+    }
+    placements
+}
+
+/// `Step::Drop`, landings are shared by every block above them in a column
+fn landing(
+    block: &Block,
+    rotation: usize,
+    board: &core::Board,
+    landings: &mut HashMap<(core::Position, usize), core::Position>,
+) -> Option<(Block, usize)> {
+    if let Some(landing) = landings.get(&(block.position.clone(), rotation)) {
+        return Some((Block::new(landing.clone(), block.block.clone()), rotation));
+    }
+    let (landing, _) = Step::Drop.apply(block, rotation, board)?;
+    for y in block.position.y..landing.position.y {
+        let position = core::Position::new(block.position.x, y);
+        landings.insert((position, rotation), landing.position.clone());
+    }
+    Some((landing, rotation))
+}
+
+#[cfg(test)]
+mod placements_tests {
+    use super::*;
+    use crate::Game;
+
+    #[test]
+    fn placements_empty_board() {
+        let board = core::Board::new();
+        assert_eq!(placements(&board, core::Tetromino::O).len(), 9);
+        assert_eq!(placements(&board, core::Tetromino::I).len(), 17);
+        assert_eq!(placements(&board, core::Tetromino::S).len(), 17);
+        assert_eq!(placements(&board, core::Tetromino::T).len(), 34);
+        assert_eq!(placements(&board, core::Tetromino::L).len(), 34);
+    }
+
+    #[test]
+    fn placements_tuck() {
+        // [][][][][][][] . . .
+        // . . . . . . . . . .
+        // . . . . . . . . . .
+        // . . . . . . . . . .
+        let i = core::Block::new_i().rotate(core::RotateDirection::Left);
+        let board = core::Board::new()
+            .set_block(&core::Position::new(0, 16), i.clone())
+            .set_block(&core::Position::new(3, 16), i);
+
+        let placements = placements(&board, core::Tetromino::O);
+        let tuck = placements
+            .iter()
+            .find(|p| p.position == core::Position::new(0, 18))
+            .unwrap();
+        assert_eq!(tuck.steps.last(), Some(&Step::DasLeft));
+        assert!(tuck.steps.contains(&Step::Drop));
+        assert!(placements
+            .iter()
+            .any(|p| p.position == core::Position::new(0, 14)));
+    }
+
+    #[test]
+    fn placements_steps() {
+        let board = core::Board::new();
+        for placement in placements(&board, core::Tetromino::T) {
+            let mut game = Game::new();
+            game.block = Some(Block::spawn(core::Tetromino::T, &board));
+            for step in &placement.steps {
+                game = game.input((*step).into());
+            }
+            let game = game.input(Step::Drop.into());
+            assert_eq!(game.block, Some(placement.block()));
+        }
+    }
+}
@@ -0,0 +1,298 @@
+use super::{core, movegen, ruleset, Game};
+use std::collections::HashSet;
+
+/// a placement of a solution
+#[derive(Debug, PartialEq, Clone)]
+pub struct Move {
+    /// swap with hold before placing
+    pub hold: bool,
+    pub placement: movegen::Placement,
+}
+
+/// searched states known to lead nowhere: the rows under the PC line, the
+/// next queue index and the held tetromino
+///
+/// the falling tetromino and the pieces left follow from the index and hold
+type Seen = HashSet<(Vec<u64>, usize, Option<u8>)>;
+
+struct Solver<'a> {
+    queue: &'a [core::Tetromino],
+    seen: Seen,
+    system: ruleset::RotationSystem,
+}
+
+impl Solver<'_> {
+    /// filled cells of the bottom `height` rows, a bit per column, top row
+    /// first
+    fn rows(board: &core::Board, height: usize) -> Vec<u64> {
+        let size = board.size;
+        (size.y - height..size.y)
+            .map(|y| {
+                (0..size.x)
+                    .filter(|x| board.state(&core::Position::new(*x, y)).is_filled())
+                    .fold(0, |row, x| row | 1 << x)
+            })
+            .collect()
+    }
+
+    /// every region of connected empty cells in `rows` takes whole
+    /// tetrominos
+    ///
+    /// regions joined by a later line clear are not considered
+    fn is_fillable(rows: &[u64], width: usize) -> bool {
+        let full = u64::MAX >> (64 - width);
+        let mut open: Vec<u64> = rows.iter().map(|row| !row & full).collect();
+        for y in 0..open.len() {
+            while open[y] != 0 {
+                let x = open[y].trailing_zeros() as usize;
+                open[y] &= !(1 << x);
+                let mut stack = vec![(x, y)];
+                let mut size: usize = 0;
+                while let Some((x, y)) = stack.pop() {
+                    size += 1;
+                    let neighbours = [
+                        (x.wrapping_sub(1), y),
+                        (x + 1, y),
+                        (x, y.wrapping_sub(1)),
+                        (x, y + 1),
+                    ];
+                    for (x, y) in neighbours {
+                        if x < width && y < open.len() && open[y] & 1 << x != 0 {
+                            open[y] &= !(1 << x);
+                            stack.push((x, y));
+                        }
+                    }
+                }
+                if !size.is_multiple_of(4) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// fill the bottom `height` rows of `board` exactly, with at most `left`
+    /// pieces
+    fn search(
+        &mut self,
+        board: &core::Board,
+        current: Option<core::Tetromino>,
+        hold: Option<core::Tetromino>,
+        index: usize,
+        left: usize,
+        height: usize,
+    ) -> Option<Vec<Move>> {
+        if board.is_empty() {
+            return Some(Vec::new());
+        }
+        let current = current?;
+        let width = board.size.x;
+        let rows = Self::rows(board, height);
+        let filled: usize = rows.iter().map(|row| row.count_ones() as usize).sum();
+        if (height * width - filled) / 4 > left || !Self::is_fillable(&rows, width) {
+            return None;
+        }
+        let key = (rows, index, hold.map(|t| t as u8));
+        if self.seen.contains(&key) {
+            return None;
+        }
+
+        let next = |i: usize| self.queue.get(i).copied();
+        // the tetromino placed, the hold after it, and the next queue index
+        let mut choices = vec![(false, current, hold, index)];
+        match hold {
+            Some(held) if held != current => choices.push((true, held, Some(current), index)),
+            None => {
+                if let Some(first) = next(index) {
+                    choices.push((true, first, Some(current), index + 1));
+                }
+            }
+            _ => {}
+        }
+
+        let line = board.size.y - height;
+        for (swap, tetromino, hold, index) in choices {
+            for placement in movegen::placements_with(board, tetromino, self.system) {
+                if placement.cells().iter().any(|p| p.y < line) {
+                    continue;
+                }
+                let (board, cleared) = placement.lock(board.clone()).remove_valid_rows();
+                let current = self.queue.get(index).copied();
+                let moves =
+                    self.search(&board, current, hold, index + 1, left - 1, height - cleared);
+                if let Some(mut moves) = moves {
+                    moves.insert(
+                        0,
+                        Move {
+                            hold: swap,
+                            placement,
+                        },
+                    );
+                    return Some(moves);
+                }
+            }
+        }
+
+        self.seen.insert(key);
+        None
+    }
+}
+
+/// placements clearing every cell of `board` with at most `pieces` blocks,
+/// starting with `current` and drawing from `queue`, with hold available
+///
+/// the lowest PC line the pieces can reach is tried first, no block goes
+/// above it; boards up to 64 wide
+///
+/// an empty board is solved by no placement
+pub fn solve(
+    board: &core::Board,
+    current: core::Tetromino,
+    hold: Option<core::Tetromino>,
+    queue: &[core::Tetromino],
+    pieces: usize,
+) -> Option<Vec<Move>> {
+    solve_with(
+        board,
+        current,
+        hold,
+        queue,
+        pieces,
+        ruleset::RotationSystem::Flip,
+    )
+}
+
+/// `solve` turning by `system`, without 180s for
+/// `ruleset::RotationSystem::Simple`
+pub fn solve_with(
+    board: &core::Board,
+    current: core::Tetromino,
+    hold: Option<core::Tetromino>,
+    queue: &[core::Tetromino],
+    pieces: usize,
+    system: ruleset::RotationSystem,
+) -> Option<Vec<Move>> {
+    if board.is_empty() {
+        return Some(Vec::new());
+    }
+    let size = board.size;
+    let rows = Solver::rows(board, size.y);
+    let filled: usize = rows.iter().map(|row| row.count_ones() as usize).sum();
+    let stack = size.y - rows.iter().take_while(|row| **row == 0).count();
+
+    let mut solver = Solver {
+        queue,
+        seen: HashSet::new(),
+        system,
+    };
+    for height in stack..=size.y {
+        let empty = height * size.x - filled;
+        if empty / 4 > pieces {
+            break;
+        }
+        if !empty.is_multiple_of(4) {
+            continue;
+        }
+        let moves = solver.search(board, Some(current), hold, 0, pieces, height);
+        if moves.is_some() {
+            return moves;
+        }
+    }
+    None
+}
+
+/// `solve` from the falling block, hold, next queue and rotation system of
+/// `game`
+pub fn solve_game(game: &Game, pieces: usize) -> Option<Vec<Move>> {
+    solve_with(
+        game.board(),
+        game.current()?,
+        game.hold(),
+        game.next(),
+        pieces,
+        game.ruleset().rotation,
+    )
+}
+
+#[cfg(test)]
+mod pc_tests {
+    use super::core::Tetromino::*;
+    use super::*;
+
+    /// the two bottom rows filled but the listed columns
+    fn board(open: &[usize]) -> core::Board {
+        let mut board = core::Board::new();
+        for y in 18..20 {
+            for x in (0..10).filter(|x| !open.contains(x)) {
+                board = board.set_state(&core::Position::new(x, y), core::State::Garbage);
+            }
+        }
+        board
+    }
+
+    /// lock the placements and return the final board
+    fn apply(board: &core::Board, moves: &[Move]) -> core::Board {
+        moves.iter().fold(board.clone(), |board, m| {
+            m.placement.lock(board).remove_valid_rows().0
+        })
+    }
+
+    #[test]
+    fn pc_solve() {
+        assert!(!Solver::is_fillable(&[0b1111111011, 0b1111111011], 10));
+        assert!(Solver::is_fillable(&[0b1111111001, 0b1111111001], 10));
+
+        assert_eq!(
+            solve(&core::Board::new(), T, None, &[], 3),
+            Some(Vec::new())
+        );
+
+        let board = board(&[8, 9]);
+        let moves = solve(&board, O, None, &[], 1).unwrap();
+        assert_eq!(moves.len(), 1);
+        assert!(apply(&board, &moves).is_empty());
+
+        assert_eq!(solve(&board, T, None, &[], 1), None);
+
+        let simple = ruleset::RotationSystem::Simple;
+        let moves = solve_with(&board, O, None, &[], 1, simple).unwrap();
+        assert!(apply(&board, &moves).is_empty());
+    }
+
+    #[test]
+    fn pc_solve_hold() {
+        let board = board(&[6, 7, 8, 9]);
+        let moves = solve(&board, T, None, &[O, O, T], 3).unwrap();
+        assert_eq!(moves.len(), 2);
+        assert!(moves[0].hold);
+        assert!(apply(&board, &moves).is_empty());
+        assert!(moves.iter().all(|m| m.placement.tetromino == O));
+
+        assert_eq!(solve(&board, T, None, &[O, O], 1), None);
+        assert_eq!(solve(&board, S, None, &[S, S], 3), None);
+    }
+
+    #[test]
+    fn pc_solve_four_lines() {
+        let mut board = core::Board::new();
+        for x in 0..4 {
+            board = board.set_state(&core::Position::new(x, 19), core::State::Garbage);
+        }
+        let moves = solve(&board, L, None, &[J, S, Z, I, O, T, T, L, J], 9).unwrap();
+        assert_eq!(moves.len(), 9);
+        assert!(apply(&board, &moves).is_empty());
+        // nothing goes above the fourth row
+        assert!(moves
+            .iter()
+            .all(|m| m.placement.cells().iter().all(|p| p.y >= 16)));
+    }
+
+    #[test]
+    fn pc_solve_game() {
+        let mut game = Game::with_sequence(&[O]).deside().0;
+        game.board = board(&[8, 9]);
+        let moves = solve_game(&game, 1).unwrap();
+        assert_eq!(moves[0].placement.tetromino, O);
+        assert_eq!(solve_game(&Game::new(), 1), None);
+    }
+}
@@ -0,0 +1,450 @@
+use super::{core, mode::GameMode, mode::Progress, ClearKind, Event, Game, TSpin};
+use serde::Deserialize;
+use std::str::FromStr;
+use std::{fs, path::Path};
+
+/// what a puzzle asks for, checked after each lock
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Goal {
+    /// remove at least this many rows
+    Lines(usize),
+    PerfectClear,
+    TSpinDouble,
+    /// use every piece without leaving a hole, failing at the first one
+    NoHoles,
+}
+
+impl FromStr for Goal {
+    type Err = String;
+
+    /// `lines N`, `pc`, `tsd` or `no-holes`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        match (words.next(), words.next(), words.next()) {
+            (Some("lines"), Some(n), None) => n
+                .parse()
+                .map(Goal::Lines)
+                .map_err(|_| format!("invalid line count: {n}")),
+            (Some("pc" | "perfect-clear"), None, None) => Ok(Goal::PerfectClear),
+            (Some("tsd" | "t-spin-double"), None, None) => Ok(Goal::TSpinDouble),
+            (Some("no-holes"), None, None) => Ok(Goal::NoHoles),
+            _ => Err(format!("unknown goal: {s}")),
+        }
+    }
+}
+
+/// a starting board, a fixed sequence and a goal, played as a `GameMode`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Puzzle {
+    pub name: String,
+    pub board: core::Board,
+    pub sequence: Vec<core::Tetromino>,
+    pub hold: bool,
+    pub goal: Goal,
+    /// success or failure once decided
+    result: Option<bool>,
+}
+
+impl Puzzle {
+    pub fn new(
+        name: &str,
+        board: core::Board,
+        sequence: Vec<core::Tetromino>,
+        hold: bool,
+        goal: Goal,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            board,
+            sequence,
+            hold,
+            goal,
+            result: None,
+        }
+    }
+
+    /// success or failure once decided
+    pub fn result(&self) -> Option<bool> {
+        self.result
+    }
+
+    fn check(&self, game: &Game, events: &[Event]) -> Option<bool> {
+        let mut locked = false;
+        for event in events {
+            match event {
+                Event::PieceLocked { .. } => locked = true,
+                Event::LinesCleared { clear, .. } => match self.goal {
+                    Goal::PerfectClear if clear.all_clear => return Some(true),
+                    Goal::TSpinDouble
+                        if clear.kind == ClearKind::Double && clear.t_spin == Some(TSpin::Full) =>
+                    {
+                        return Some(true)
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        if !locked {
+            return None;
+        }
+
+        match self.goal {
+            Goal::Lines(n) if game.lines() >= n => return Some(true),
+            Goal::NoHoles if game.board().metrics().holes > 0 => return Some(false),
+            _ => {}
+        }
+        // the block is locked and no other one is coming
+        if game.current().is_none() && game.next().is_empty() {
+            return Some(self.goal == Goal::NoHoles);
+        }
+        None
+    }
+}
+
+impl GameMode for Puzzle {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// the puzzle's board and sequence replace `game`
+    fn setup(&mut self, _: Game) -> Game {
+        self.result = None;
+        let game = Game {
+            board: self.board.clone(),
+            ..Game::with_sequence(&self.sequence)
+        };
+        match self.hold {
+            true => game,
+            false => game.without_hold(),
+        }
+    }
+
+    fn update(&mut self, game: Game, events: &[Event]) -> Game {
+        if self.result.is_none() {
+            self.result = self.check(&game, events);
+        }
+        game
+    }
+
+    fn is_finished(&self, _: &Progress) -> bool {
+        self.result == Some(true)
+    }
+
+    fn is_failed(&self, _: &Progress) -> bool {
+        self.result == Some(false)
+    }
+}
+
+/// puzzle as written in a pack
+#[derive(Debug, Default, Deserialize)]
+struct Definition {
+    #[serde(default)]
+    name: String,
+    /// rows from top to bottom, lined up with the floor
+    #[serde(default)]
+    board: Vec<String>,
+    sequence: String,
+    #[serde(default = "Definition::hold")]
+    hold: bool,
+    goal: String,
+}
+
+impl Definition {
+    fn hold() -> bool {
+        true
+    }
+
+    fn tetromino(c: char) -> Option<core::Tetromino> {
+        match c.to_ascii_uppercase() {
+            'I' => Some(core::Tetromino::I),
+            'O' => Some(core::Tetromino::O),
+            'T' => Some(core::Tetromino::T),
+            'J' => Some(core::Tetromino::J),
+            'L' => Some(core::Tetromino::L),
+            'S' => Some(core::Tetromino::S),
+            'Z' => Some(core::Tetromino::Z),
+            _ => None,
+        }
+    }
+
+    fn puzzle(self) -> Result<Puzzle, String> {
+        let mut board = core::Board::new();
+        let size = board.size;
+        if self.board.len() > size.y {
+            return Err(format!("{}: more than {} rows", self.name, size.y));
+        }
+        let top = size.y - self.board.len();
+        for (y, row) in self.board.iter().enumerate() {
+            if row.chars().count() != size.x {
+                return Err(format!("{}: row is not {} wide: {row}", self.name, size.x));
+            }
+            for (x, c) in row.chars().enumerate() {
+                let state = match c {
+                    '.' | '_' => continue,
+                    'X' | 'G' | '#' => core::State::Garbage,
+                    c => match Self::tetromino(c) {
+                        Some(t) => core::State::Block(t),
+                        None => return Err(format!("{}: unknown cell: {c}", self.name)),
+                    },
+                };
+                board = board.set_state(&core::Position::new(x, top + y), state);
+            }
+        }
+
+        let sequence = self
+            .sequence
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != ',')
+            .map(|c| Self::tetromino(c).ok_or(format!("{}: unknown piece: {c}", self.name)))
+            .collect::<Result<Vec<_>, _>>()?;
+        if sequence.is_empty() {
+            return Err(format!("{}: empty sequence", self.name));
+        }
+        let goal = self
+            .goal
+            .parse()
+            .map_err(|e| format!("{}: {e}", self.name))?;
+
+        Ok(Puzzle::new(&self.name, board, sequence, self.hold, goal))
+    }
+}
+
+/// puzzles read from a JSON array of objects with `name`, `board`,
+/// `sequence`, `hold` and `goal`, or from text like:
+///
+/// ```text
+/// # blank lines separate puzzles
+/// name: first T-spin double
+/// goal: tsd
+/// hold: no
+/// sequence: T
+/// board:
+/// XXXX.XXXXX
+/// XXX...XXXX
+/// ```
+///
+/// rows are lined up with the floor, `.` is empty, `X` garbage and a
+/// tetromino letter a block of it
+pub fn parse(text: &str) -> Result<Vec<Puzzle>, String> {
+    let text = text.trim_start();
+    if text.starts_with('[') {
+        let definitions: Vec<Definition> = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        return definitions.into_iter().map(Definition::puzzle).collect();
+    }
+
+    let mut puzzles = Vec::new();
+    let mut definition: Option<Definition> = None;
+    let mut board = false;
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('#') {
+            continue;
+        }
+        if line.is_empty() {
+            if let Some(definition) = definition.take() {
+                puzzles.push(definition.puzzle()?);
+            }
+            board = false;
+            continue;
+        }
+
+        let current = definition.get_or_insert_with(|| Definition {
+            hold: true,
+            ..Definition::default()
+        });
+        match line.split_once(':') {
+            Some((key, value)) => {
+                let value = value.trim().to_string();
+                board = false;
+                match key.trim() {
+                    "name" => current.name = value,
+                    "goal" => current.goal = value,
+                    "sequence" => current.sequence = value,
+                    "hold" => {
+                        current.hold = match value.as_str() {
+                            "yes" | "true" => true,
+                            "no" | "false" => false,
+                            _ => return Err(format!("invalid hold: {value}")),
+                        }
+                    }
+                    "board" => board = true,
+                    key => return Err(format!("unknown key: {key}")),
+                }
+            }
+            None if board => current.board.push(line.to_string()),
+            None => return Err(format!("unexpected line: {line}")),
+        }
+    }
+    if let Some(definition) = definition {
+        puzzles.push(definition.puzzle()?);
+    }
+    Ok(puzzles)
+}
+
+/// read a puzzle pack, text or JSON
+pub fn load(path: impl AsRef<Path>) -> Result<Vec<Puzzle>, String> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    parse(&text)
+}
+
+#[cfg(test)]
+mod puzzle_tests {
+    use super::*;
+    use crate::mode::Session;
+    use crate::movegen::{self, Step};
+
+    const PACK: &str = "
+        # a T-spin double slot, the T rests on it and flips in
+        name: tsd
+        goal: tsd
+        hold: no
+        sequence: T
+        board:
+        XXXX.XXXXX
+        XXX...XXXX
+
+        name: two lines
+        goal: lines 2
+        sequence: I O
+        board:
+        XXXXXXXX..
+        XXXXXXXX..
+    ";
+
+    /// place the falling block with the first placement whose cells `f`
+    /// accepts
+    fn place(session: &mut Session, f: impl Fn(&[core::Position]) -> bool) {
+        if session.game().current().is_none() {
+            session.deside();
+        }
+        let game = session.game();
+        let placement = movegen::placements(game.board(), game.current().unwrap())
+            .into_iter()
+            .find(|placement| f(&placement.cells()))
+            .unwrap();
+        for step in placement.steps {
+            session.input(step.into());
+        }
+        session.input(Step::Drop.into());
+        session.deside();
+    }
+
+    #[test]
+    fn puzzle_parse() {
+        let puzzles = parse(PACK).unwrap();
+        assert_eq!(puzzles.len(), 2);
+        assert_eq!(puzzles[0].goal, Goal::TSpinDouble);
+        assert!(!puzzles[0].hold);
+        assert_eq!(puzzles[1].goal, Goal::Lines(2));
+        assert_eq!(
+            puzzles[1].sequence,
+            vec![core::Tetromino::I, core::Tetromino::O]
+        );
+        assert_eq!(
+            puzzles[1].board.state(&core::Position::new(0, 19)),
+            core::State::Garbage
+        );
+
+        let json = r#"[{"name": "pc", "board": ["IIII......"], "sequence": "IO", "goal": "pc"}]"#;
+        let puzzles = parse(json).unwrap();
+        assert_eq!(puzzles[0].goal, Goal::PerfectClear);
+        assert!(puzzles[0].hold);
+        assert_eq!(
+            puzzles[0].board.state(&core::Position::new(3, 19)),
+            core::State::Block(core::Tetromino::I)
+        );
+
+        assert!(parse("goal: lines\nsequence: T").is_err());
+        assert!(parse("goal: pc\nsequence: T\nboard:\nXX").is_err());
+    }
+
+    fn xs(cells: &[core::Position]) -> Vec<usize> {
+        let mut xs: Vec<_> = cells.iter().map(|p| p.x).collect();
+        xs.sort();
+        xs.dedup();
+        xs
+    }
+
+    #[test]
+    fn puzzle_goal() {
+        let puzzles = parse(PACK).unwrap();
+
+        // checked from the clear events
+        let mut tsd = puzzles[0].clone();
+        let game = tsd.setup(Game::new()).deside().0;
+        let clear = crate::Clear {
+            kind: ClearKind::Double,
+            t_spin: Some(TSpin::Mini),
+            combo: 0,
+            back_to_back: false,
+            all_clear: false,
+        };
+        let events = |clear: &crate::Clear| {
+            vec![
+                Event::PieceLocked {
+                    tetromino: core::Tetromino::T,
+                    position: core::Position::new(2, 18),
+                },
+                Event::LinesCleared {
+                    rows: vec![18, 19],
+                    clear: clear.clone(),
+                },
+            ]
+        };
+        let game = tsd.update(game, &events(&clear));
+        assert_eq!(tsd.result(), None);
+        let full = crate::Clear {
+            t_spin: Some(TSpin::Full),
+            ..clear
+        };
+        tsd.update(game, &events(&full));
+        assert_eq!(tsd.result(), Some(true));
+
+        // the O fills the well
+        let mut session = Session::new(Box::new(puzzles[1].clone()));
+        place(&mut session, |cells| xs(cells) == vec![0, 1, 2, 3]);
+        assert!(!session.is_over());
+        place(&mut session, |cells| xs(cells) == vec![8, 9]);
+        assert!(session.is_finished());
+
+        // the O lands elsewhere and the pieces are used up
+        let mut session = Session::new(Box::new(puzzles[1].clone()));
+        place(&mut session, |cells| xs(cells) == vec![0, 1, 2, 3]);
+        place(&mut session, |cells| xs(cells) == vec![0, 1]);
+        assert!(session.is_failed());
+        assert!(session.outcome().is_some_and(|outcome| !outcome.finished));
+    }
+
+    #[test]
+    fn puzzle_tsd() {
+        let puzzle = parse(PACK).unwrap().remove(0);
+        let mut session = Session::new(Box::new(puzzle));
+        session.deside();
+        assert_eq!(session.game().current(), Some(core::Tetromino::T));
+
+        // down onto the slot, then a 180 kicked into it
+        session.input(Step::Drop.into());
+        session.input(Step::RotateFlip.into());
+        session.deside();
+        assert!(session.is_finished());
+        assert!(session.game().board().is_empty());
+    }
+
+    #[test]
+    fn puzzle_no_holes() {
+        let puzzle = Puzzle::new(
+            "flat",
+            core::Board::new(),
+            vec![core::Tetromino::O, core::Tetromino::S],
+            false,
+            Goal::NoHoles,
+        );
+        let mut session = Session::new(Box::new(puzzle));
+        place(&mut session, |cells| xs(cells) == vec![0, 1]);
+        assert!(!session.is_over());
+        // any S leaves a hole next to the O
+        place(&mut session, |_| true);
+        assert!(session.is_failed());
+    }
+}
@@ -0,0 +1,162 @@
+use super::core;
+use rand::{seq::SliceRandom, Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// how the upcoming tetrominos are drawn
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Randomizer {
+    /// each tetromino independently
+    #[default]
+    Random,
+    /// shuffled bags holding each of the 7 tetrominos once
+    Bag,
+}
+
+impl Randomizer {
+    pub const ALL: [Randomizer; 2] = [Randomizer::Random, Randomizer::Bag];
+}
+
+impl FromStr for Randomizer {
+    type Err = String;
+
+    /// `random` or `bag`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "random" => Ok(Randomizer::Random),
+            "bag" => Ok(Randomizer::Bag),
+            _ => Err(format!("unknown randomizer: {s}")),
+        }
+    }
+}
+
+/// small deterministic generator, so seeded games can be compared and cloned
+#[derive(Debug, PartialEq, Clone)]
+struct SplitMix64(u64);
+
+impl RngCore for SplitMix64 {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// source of tetrominos for a game, seeded or from the thread generator
+#[derive(Debug, PartialEq, Clone, Default)]
+pub(crate) struct Generator {
+    randomizer: Randomizer,
+    rng: Option<SplitMix64>,
+    bag: Vec<core::Tetromino>,
+    /// draw from the bag only, ending once it is empty
+    fixed: bool,
+}
+
+impl Generator {
+    pub(crate) fn new(randomizer: Randomizer, seed: Option<u64>) -> Self {
+        Self {
+            randomizer,
+            rng: seed.map(SplitMix64),
+            bag: Vec::with_capacity(7),
+            fixed: false,
+        }
+    }
+
+    /// the tetrominos of `sequence` in order and no more
+    pub(crate) fn fixed(sequence: &[core::Tetromino]) -> Self {
+        Self {
+            bag: sequence.iter().rev().copied().collect(),
+            fixed: true,
+            ..Self::default()
+        }
+    }
+
+    /// run `f` with the seeded generator, or the thread one
+    pub(crate) fn with_rng<T>(&mut self, f: impl FnOnce(&mut dyn RngCore) -> T) -> T {
+        match &mut self.rng {
+            Some(rng) => f(rng),
+            None => f(&mut rand::thread_rng()),
+        }
+    }
+
+    /// `None` once a fixed sequence is used up
+    pub(crate) fn next(&mut self) -> Option<core::Tetromino> {
+        if self.fixed {
+            return self.bag.pop();
+        }
+        match self.randomizer {
+            Randomizer::Random => Some(self.with_rng(|rng| rng.gen())),
+            Randomizer::Bag => {
+                if self.bag.is_empty() {
+                    let mut bag = vec![
+                        core::Tetromino::I,
+                        core::Tetromino::O,
+                        core::Tetromino::T,
+                        core::Tetromino::J,
+                        core::Tetromino::L,
+                        core::Tetromino::S,
+                        core::Tetromino::Z,
+                    ];
+                    self.with_rng(|rng| bag.shuffle(rng));
+                    self.bag = bag;
+                }
+                self.bag.pop()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod randomizer_tests {
+    use super::*;
+
+    #[test]
+    fn randomizer_seed() {
+        let draw = |randomizer, seed| {
+            let mut generator = Generator::new(randomizer, Some(seed));
+            (0..70)
+                .map(|_| generator.next().unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(draw(Randomizer::Random, 1), draw(Randomizer::Random, 1));
+        assert_ne!(draw(Randomizer::Random, 1), draw(Randomizer::Random, 2));
+        assert_eq!(draw(Randomizer::Bag, 1), draw(Randomizer::Bag, 1));
+    }
+
+    #[test]
+    fn randomizer_bag() {
+        let mut generator = Generator::new(Randomizer::Bag, None);
+        for _ in 0..10 {
+            let mut bag: Vec<_> = (0..7).map(|_| generator.next().unwrap() as u8).collect();
+            bag.sort();
+            assert_eq!(bag, vec![0, 1, 2, 3, 4, 5, 6]);
+        }
+    }
+
+    #[test]
+    fn randomizer_fixed() {
+        use super::core::Tetromino::*;
+        let mut generator = Generator::fixed(&[T, I, O]);
+        let drawn: Vec<_> = (0..4).map(|_| generator.next()).collect();
+        assert_eq!(drawn, vec![Some(T), Some(I), Some(O), None]);
+    }
+}
@@ -0,0 +1,232 @@
+use super::{handling, randomizer, timing, NEXT_SIZE};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// how blocks turn
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RotationSystem {
+    /// inside the bounding box of the block, without kicks or 180s
+    Simple,
+    /// `Simple` and the 180 rotation with its own kicks
+    #[default]
+    Flip,
+}
+
+/// everything a game plays by, see the presets for the usual ones
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Ruleset {
+    pub width: usize,
+    pub height: usize,
+    pub randomizer: randomizer::Randomizer,
+    pub rotation: RotationSystem,
+    /// frames a landed block waits before locking, 0 locks it on the next
+    /// gravity tick; dropping a row starts the wait again
+    pub lock_delay: usize,
+    /// milliseconds between gravity ticks from level 1 on, the last one
+    /// holds for every level past it
+    pub gravity: Vec<u64>,
+    /// score for 0, 1, 2, 3 and 4 removed rows, multiplied by the level
+    pub score: [usize; 5],
+    /// tetrominos shown in the next queue
+    pub preview: usize,
+    pub hold: bool,
+    /// default DAS, ARR and soft drop factor
+    pub handling: handling::Handling,
+    pub delays: timing::Delays,
+}
+
+impl Default for Ruleset {
+    fn default() -> Self {
+        Self::classic()
+    }
+}
+
+impl Ruleset {
+    /// names `Ruleset::preset` knows
+    pub const PRESETS: [&'static str; 4] = ["classic", "guideline", "nes", "tgm"];
+
+    /// the default: random pieces, hold, five previews and the 180, no lock
+    /// or entry delay, and gravity from a second a row down by 75 ms a level
+    pub fn classic() -> Self {
+        Self {
+            width: 10,
+            height: 20,
+            randomizer: randomizer::Randomizer::Random,
+            rotation: RotationSystem::Flip,
+            lock_delay: 0,
+            gravity: (0..13).map(|level| 1_000 - 75 * level).collect(),
+            score: [0, 100, 300, 500, 800],
+            preview: NEXT_SIZE,
+            hold: true,
+            handling: handling::Handling::default(),
+            delays: timing::Delays::default(),
+        }
+    }
+
+    /// 7-bag, half a second of lock delay and the guideline speed curve
+    pub fn guideline() -> Self {
+        Self {
+            randomizer: randomizer::Randomizer::Bag,
+            lock_delay: 30,
+            gravity: vec![
+                1_000, 793, 618, 473, 355, 262, 190, 135, 94, 64, 43, 28, 18, 11, 7,
+            ],
+            ..Self::classic()
+        }
+    }
+
+    /// one preview, no hold and the NES speeds, level 1 being NES level 0
+    pub fn nes() -> Self {
+        Self {
+            rotation: RotationSystem::Simple,
+            gravity: vec![
+                799, 716, 632, 549, 466, 383, 300, 216, 133, 100, 83, 83, 83, 67, 67, 67, 50, 50,
+                50, 33,
+            ],
+            score: [0, 40, 100, 300, 1_200],
+            preview: 1,
+            hold: false,
+            handling: handling::Handling {
+                das: 16,
+                arr: 6,
+                soft_drop_factor: 20,
+            },
+            delays: timing::Delays {
+                are: 10,
+                line_clear: 20,
+            },
+            ..Self::classic()
+        }
+    }
+
+    /// one preview, no hold, long delays and a quick climb to a row a frame
+    pub fn tgm() -> Self {
+        Self {
+            randomizer: randomizer::Randomizer::Bag,
+            rotation: RotationSystem::Simple,
+            lock_delay: 30,
+            gravity: vec![1_067, 533, 267, 133, 67, 33, 17],
+            preview: 1,
+            hold: false,
+            handling: handling::Handling {
+                das: 14,
+                arr: 1,
+                soft_drop_factor: 20,
+            },
+            delays: timing::Delays {
+                are: 30,
+                line_clear: 41,
+            },
+            ..Self::classic()
+        }
+    }
+
+    /// the preset called `name`, one of `Ruleset::PRESETS`
+    pub fn preset(name: &str) -> Result<Self, String> {
+        match name {
+            "classic" => Ok(Self::classic()),
+            "guideline" => Ok(Self::guideline()),
+            "nes" => Ok(Self::nes()),
+            "tgm" => Ok(Self::tgm()),
+            _ => Err(format!("unknown ruleset: {name}")),
+        }
+    }
+
+    /// time between gravity ticks at `level`
+    pub fn gravity(&self, level: usize) -> Duration {
+        let index = level.max(1) - 1;
+        let ms = self
+            .gravity
+            .get(index)
+            .or(self.gravity.last())
+            .copied()
+            .unwrap_or(1_000);
+        Duration::from_millis(ms)
+    }
+
+    /// points for a lock removing `rows` at `level`
+    pub fn score(&self, rows: usize, level: usize) -> usize {
+        self.score[rows.min(4)] * level
+    }
+
+    /// read rules from JSON or TOML, fields left out are the classic ones
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let ruleset: Self = match text.trim_start().starts_with('{') {
+            true => serde_json::from_str(text).map_err(|e| e.to_string())?,
+            false => toml::from_str(text).map_err(|e| e.to_string())?,
+        };
+        if ruleset.width < 4 || ruleset.height < 4 {
+            return Err(format!(
+                "board too small: {}x{}",
+                ruleset.width, ruleset.height
+            ));
+        }
+        Ok(ruleset)
+    }
+
+    /// read rules from a file, JSON or TOML
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        Self::parse(&text)
+    }
+}
+
+#[cfg(test)]
+mod ruleset_tests {
+    use super::*;
+
+    #[test]
+    fn ruleset_gravity() {
+        let ruleset = Ruleset::classic();
+        assert_eq!(ruleset.gravity(0), Duration::from_millis(1_000));
+        assert_eq!(ruleset.gravity(1), Duration::from_millis(1_000));
+        assert_eq!(ruleset.gravity(2), Duration::from_millis(925));
+        assert_eq!(ruleset.gravity(13), Duration::from_millis(100));
+        assert_eq!(ruleset.gravity(99), Duration::from_millis(100));
+        assert_eq!(Ruleset::nes().score(4, 1), 1_200);
+    }
+
+    #[test]
+    fn ruleset_parse() {
+        for name in Ruleset::PRESETS {
+            let ruleset = Ruleset::preset(name).unwrap();
+            let json = serde_json::to_string(&ruleset).unwrap();
+            assert_eq!(Ruleset::parse(&json), Ok(ruleset.clone()));
+            let toml = toml::to_string(&ruleset).unwrap();
+            assert_eq!(Ruleset::parse(&toml), Ok(ruleset));
+        }
+        assert!(Ruleset::preset("sega").is_err());
+
+        let ruleset = Ruleset::parse(
+            r#"
+            height = 24
+            randomizer = "bag"
+            rotation = "simple"
+            hold = false
+
+            [delays]
+            are = 10
+            "#,
+        )
+        .unwrap();
+        assert_eq!(ruleset.height, 24);
+        assert_eq!(ruleset.randomizer, randomizer::Randomizer::Bag);
+        assert_eq!(ruleset.rotation, RotationSystem::Simple);
+        assert!(!ruleset.hold);
+        assert_eq!(ruleset.delays.are, 10);
+        assert_eq!(ruleset.delays.line_clear, 0);
+        assert_eq!(ruleset.width, 10);
+
+        let ruleset = Ruleset::parse(r#"{"preview": 3, "score": [0, 1, 2, 3, 4]}"#).unwrap();
+        assert_eq!(ruleset.preview, 3);
+        assert_eq!(ruleset.score(2, 3), 6);
+
+        assert!(Ruleset::parse("width = 2").is_err());
+        assert!(Ruleset::parse("width = \"wide\"").is_err());
+    }
+}
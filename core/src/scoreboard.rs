@@ -0,0 +1,251 @@
+use super::mode::{Metric, Outcome};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fs;
+use std::path::PathBuf;
+
+/// a session kept on the scoreboard
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    /// `GameMode::name`, entries are ranked against the same mode only
+    pub mode: String,
+    pub metric: Metric,
+    pub finished: bool,
+    pub score: usize,
+    pub lines: usize,
+    /// engine clock in milliseconds
+    pub time: u64,
+    /// seconds since the unix epoch, when the session ended
+    pub date: u64,
+}
+
+impl Entry {
+    /// entry of a session ending at `date`
+    pub fn new(outcome: &Outcome, date: u64) -> Self {
+        Self {
+            mode: outcome.mode.clone(),
+            metric: outcome.metric,
+            finished: outcome.finished,
+            score: outcome.progress.score,
+            lines: outcome.progress.lines,
+            time: outcome.progress.elapsed.as_millis() as u64,
+            date,
+        }
+    }
+
+    /// `Less` when `self` ranks above `other`
+    ///
+    /// by time, finished sessions come first, unfinished ones by lines;
+    /// by score, the earlier of equal ones ranks higher
+    pub fn rank(&self, other: &Self) -> Ordering {
+        let by = match self.metric {
+            Metric::Time => other
+                .finished
+                .cmp(&self.finished)
+                .then(match self.finished {
+                    true => self.time.cmp(&other.time),
+                    false => other.lines.cmp(&self.lines),
+                }),
+            Metric::Score => other.score.cmp(&self.score),
+        };
+        by.then(self.date.cmp(&other.date))
+    }
+}
+
+/// where a scoreboard is kept, as JSON text
+pub trait Storage {
+    /// the saved text, `None` when nothing was saved yet
+    fn load(&self) -> Result<Option<String>, String>;
+    fn save(&mut self, text: &str) -> Result<(), String>;
+}
+
+/// a JSON file, for native front ends
+#[derive(Debug, PartialEq, Clone)]
+pub struct FileStorage {
+    pub path: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Storage for FileStorage {
+    fn load(&self) -> Result<Option<String>, String> {
+        match fs::read_to_string(&self.path) {
+            Ok(text) => Ok(Some(text)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("{}: {e}", self.path.display())),
+        }
+    }
+
+    fn save(&mut self, text: &str) -> Result<(), String> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+        }
+        fs::write(&self.path, text).map_err(|e| format!("{}: {e}", self.path.display()))
+    }
+}
+
+/// text held in memory, gone with the process
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct MemoryStorage(pub Option<String>);
+
+impl Storage for MemoryStorage {
+    fn load(&self) -> Result<Option<String>, String> {
+        Ok(self.0.clone())
+    }
+
+    fn save(&mut self, text: &str) -> Result<(), String> {
+        self.0 = Some(text.to_string());
+        Ok(())
+    }
+}
+
+/// every recorded session, in the order they were added
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Scoreboard {
+    entries: Vec<Entry>,
+}
+
+impl Scoreboard {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        serde_json::from_str(text).map_err(|e| e.to_string())
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// the scoreboard in `storage`, empty if nothing was saved there yet
+    pub fn load(storage: &dyn Storage) -> Result<Self, String> {
+        match storage.load()? {
+            Some(text) => Self::parse(&text),
+            None => Ok(Self::default()),
+        }
+    }
+
+    pub fn save(&self, storage: &mut dyn Storage) -> Result<(), String> {
+        storage.save(&self.to_json())
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// record `entry`, return its place in the ranking of its mode, from 1
+    pub fn add(&mut self, entry: Entry) -> usize {
+        let place = self
+            .entries
+            .iter()
+            .filter(|other| other.mode == entry.mode && other.rank(&entry).is_le())
+            .count()
+            + 1;
+        self.entries.push(entry);
+        place
+    }
+
+    /// entries of `mode`, best first
+    pub fn ranking(&self, mode: &str) -> Vec<&Entry> {
+        let mut entries: Vec<_> = self.entries.iter().filter(|e| e.mode == mode).collect();
+        entries.sort_by(|a, b| a.rank(b));
+        entries
+    }
+
+    /// best entry of `mode`
+    pub fn personal_best(&self, mode: &str) -> Option<&Entry> {
+        self.entries
+            .iter()
+            .filter(|e| e.mode == mode)
+            .min_by(|a, b| a.rank(b))
+    }
+
+    /// names of the modes with entries, in the order first played
+    pub fn modes(&self) -> Vec<&str> {
+        let mut modes: Vec<&str> = Vec::new();
+        for entry in &self.entries {
+            if !modes.contains(&entry.mode.as_str()) {
+                modes.push(&entry.mode);
+            }
+        }
+        modes
+    }
+}
+
+#[cfg(test)]
+mod scoreboard_tests {
+    use super::*;
+    use crate::mode::{GameMode, Marathon, Sprint, Ultra};
+
+    fn sprint(finished: bool, lines: usize, time: u64, date: u64) -> Entry {
+        let mode = Sprint::default();
+        Entry {
+            mode: mode.name(),
+            metric: mode.metric(),
+            finished,
+            score: 0,
+            lines,
+            time,
+            date,
+        }
+    }
+
+    #[test]
+    fn scoreboard_ranking() {
+        let mut board = Scoreboard::default();
+        assert_eq!(board.add(sprint(true, 40, 60_000, 1)), 1);
+        assert_eq!(board.add(sprint(false, 30, 10_000, 2)), 2);
+        assert_eq!(board.add(sprint(true, 40, 50_000, 3)), 1);
+        assert_eq!(board.add(sprint(false, 35, 20_000, 4)), 3);
+        // a tie ranks below the earlier entry
+        assert_eq!(board.add(sprint(true, 40, 50_000, 5)), 2);
+
+        let ultra = Entry {
+            mode: Ultra::default().name(),
+            metric: Ultra::default().metric(),
+            score: 1_000,
+            ..sprint(true, 10, 120_000, 6)
+        };
+        assert_eq!(board.add(ultra.clone()), 1);
+        assert_eq!(
+            board.add(Entry {
+                score: 2_000,
+                ..ultra.clone()
+            }),
+            1
+        );
+
+        let (sprint, ultra) = (Sprint::default().name(), ultra.mode);
+        let dates: Vec<_> = board.ranking(&sprint).iter().map(|e| e.date).collect();
+        assert_eq!(dates, vec![3, 5, 1, 4, 2]);
+        assert_eq!(board.personal_best(&sprint).unwrap().date, 3);
+        assert_eq!(board.personal_best(&ultra).unwrap().score, 2_000);
+        assert_eq!(board.personal_best(&Marathon::default().name()), None);
+        assert_eq!(board.modes(), vec![sprint, ultra]);
+    }
+
+    #[test]
+    fn scoreboard_storage() {
+        let mut storage = MemoryStorage::default();
+        assert_eq!(Scoreboard::load(&storage), Ok(Scoreboard::default()));
+
+        let mut board = Scoreboard::default();
+        board.add(sprint(true, 40, 60_000, 1));
+        board.save(&mut storage).unwrap();
+        assert_eq!(Scoreboard::load(&storage), Ok(board.clone()));
+
+        let path = std::env::temp_dir()
+            .join(format!("tetris-scoreboard-{}", std::process::id()))
+            .join("scores.json");
+        let mut file = FileStorage::new(&path);
+        assert_eq!(file.load(), Ok(None));
+        board.save(&mut file).unwrap();
+        assert_eq!(Scoreboard::load(&file), Ok(board));
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        storage.0 = Some("{".to_string());
+        assert!(Scoreboard::load(&storage).is_err());
+    }
+}
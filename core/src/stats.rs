@@ -0,0 +1,193 @@
+use super::{core, versus, ClearKind, Event, Observer, TSpin};
+use std::time::Duration;
+
+/// clears counted by kind, T-spins apart from the plain line clears
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Clears {
+    pub singles: usize,
+    pub doubles: usize,
+    pub triples: usize,
+    pub tetrises: usize,
+    pub t_spin_singles: usize,
+    pub t_spin_doubles: usize,
+    pub t_spin_triples: usize,
+    pub t_spin_minis: usize,
+    /// also counted by their kind
+    pub all_clears: usize,
+}
+
+/// what a game did, fed by its events as an `Observer`
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Stats {
+    /// locked blocks
+    pub pieces: usize,
+    /// locked blocks by tetromino, indexed by `Tetromino as usize`
+    pub distribution: [usize; 7],
+    pub lines: usize,
+    pub clears: Clears,
+    /// key presses spent on the locked blocks
+    pub keys: usize,
+    /// garbage rows the clears would send, by the `versus::AttackTable`
+    pub attack: usize,
+    pub max_combo: usize,
+    /// longest run of back-to-back clears
+    pub max_back_to_back: usize,
+    /// engine clock, set by `mode::Session`
+    pub duration: Duration,
+    table: versus::AttackTable,
+    back_to_back: usize,
+}
+
+impl Stats {
+    /// stats counting attack by `table`
+    pub fn with_table(table: versus::AttackTable) -> Self {
+        Self {
+            table,
+            ..Self::default()
+        }
+    }
+
+    /// locked blocks of `tetromino`
+    pub fn count(&self, tetromino: core::Tetromino) -> usize {
+        self.distribution[tetromino as usize]
+    }
+
+    /// per second of `duration`, 0 before the clock started
+    fn per_second(&self, n: usize) -> f64 {
+        match self.duration.as_secs_f64() {
+            secs if secs > 0.0 => n as f64 / secs,
+            _ => 0.0,
+        }
+    }
+
+    /// pieces per second
+    pub fn pps(&self) -> f64 {
+        self.per_second(self.pieces)
+    }
+
+    /// keys per piece
+    pub fn kpp(&self) -> f64 {
+        match self.pieces {
+            0 => 0.0,
+            pieces => self.keys as f64 / pieces as f64,
+        }
+    }
+
+    /// attack per minute
+    pub fn apm(&self) -> f64 {
+        self.per_second(self.attack) * 60.0
+    }
+}
+
+impl Observer for Stats {
+    fn notify(&mut self, event: Event) {
+        match event {
+            Event::PieceLocked { tetromino, .. } => {
+                self.pieces += 1;
+                self.distribution[tetromino as usize] += 1;
+            }
+            Event::Finesse(finesse) => self.keys += finesse.keys,
+            Event::LinesCleared { clear, .. } => {
+                self.lines += clear.kind.rows();
+                let count = match (clear.t_spin, clear.kind) {
+                    (Some(TSpin::Full), ClearKind::Single) => &mut self.clears.t_spin_singles,
+                    (Some(TSpin::Full), ClearKind::Double) => &mut self.clears.t_spin_doubles,
+                    // a T spans three rows at most
+                    (Some(TSpin::Full), ClearKind::Triple | ClearKind::Tetris) => {
+                        &mut self.clears.t_spin_triples
+                    }
+                    (Some(TSpin::Mini), _) => &mut self.clears.t_spin_minis,
+                    (None, ClearKind::Single) => &mut self.clears.singles,
+                    (None, ClearKind::Double) => &mut self.clears.doubles,
+                    (None, ClearKind::Triple) => &mut self.clears.triples,
+                    (None, ClearKind::Tetris) => &mut self.clears.tetrises,
+                };
+                *count += 1;
+                if clear.all_clear {
+                    self.clears.all_clears += 1;
+                }
+                self.attack += self.table.attack(&clear);
+                self.max_combo = self.max_combo.max(clear.combo);
+                self.back_to_back = match clear.back_to_back {
+                    true => self.back_to_back + 1,
+                    false => 0,
+                };
+                self.max_back_to_back = self.max_back_to_back.max(self.back_to_back);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod stats_tests {
+    use super::*;
+    use crate::Clear;
+
+    fn cleared(kind: ClearKind, combo: usize, back_to_back: bool) -> Event {
+        t_spun(kind, None, combo, back_to_back)
+    }
+
+    fn t_spun(kind: ClearKind, t_spin: Option<TSpin>, combo: usize, back_to_back: bool) -> Event {
+        Event::LinesCleared {
+            rows: Vec::new(),
+            clear: Clear {
+                kind,
+                t_spin,
+                combo,
+                back_to_back,
+                all_clear: false,
+            },
+        }
+    }
+
+    #[test]
+    fn stats_events() {
+        let mut stats = Stats::default();
+        for tetromino in [core::Tetromino::I, core::Tetromino::I, core::Tetromino::T] {
+            stats.notify(Event::PieceLocked {
+                tetromino,
+                position: core::Position::new(0, 0),
+            });
+        }
+        stats.notify(Event::Finesse(crate::finesse::Finesse {
+            tetromino: core::Tetromino::T,
+            keys: 6,
+            optimal: 2,
+        }));
+        stats.notify(cleared(ClearKind::Tetris, 0, false));
+        stats.notify(cleared(ClearKind::Tetris, 1, true));
+        stats.notify(cleared(ClearKind::Single, 2, false));
+        stats.duration = Duration::from_secs(2);
+
+        assert_eq!(stats.count(core::Tetromino::I), 2);
+        assert_eq!(stats.count(core::Tetromino::O), 0);
+        assert_eq!(stats.lines, 9);
+        assert_eq!(stats.clears.tetrises, 2);
+        assert_eq!(stats.clears.singles, 1);
+        assert_eq!(stats.attack, 4 + 5 + 1);
+        assert_eq!(stats.max_combo, 2);
+        assert_eq!(stats.max_back_to_back, 1);
+        assert_eq!(stats.pps(), 1.5);
+        assert_eq!(stats.kpp(), 2.0);
+        assert_eq!(stats.apm(), 300.0);
+        assert_eq!(Stats::default().pps(), 0.0);
+    }
+
+    #[test]
+    fn stats_t_spins() {
+        let mut stats = Stats::default();
+        stats.notify(t_spun(ClearKind::Double, Some(TSpin::Full), 0, false));
+        stats.notify(t_spun(ClearKind::Triple, Some(TSpin::Full), 1, true));
+        stats.notify(t_spun(ClearKind::Single, Some(TSpin::Full), 2, true));
+        stats.notify(t_spun(ClearKind::Single, Some(TSpin::Mini), 3, true));
+
+        assert_eq!(stats.lines, 7);
+        assert_eq!(stats.clears.t_spin_singles, 1);
+        assert_eq!(stats.clears.t_spin_doubles, 1);
+        assert_eq!(stats.clears.t_spin_triples, 1);
+        assert_eq!(stats.clears.t_spin_minis, 1);
+        assert_eq!(stats.clears.singles, 0);
+        assert_eq!(stats.max_back_to_back, 3);
+    }
+}
@@ -0,0 +1,703 @@
+use super::{bot::Bot, core, movegen, Game, Input};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+/// rows of a TBP board, this board uses the bottom rows only
+const BOARD_HEIGHT: usize = 40;
+
+/// cell of a TBP board, `G` for garbage
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum Cell {
+    I,
+    O,
+    T,
+    J,
+    L,
+    S,
+    Z,
+    G,
+}
+
+impl From<core::Tetromino> for Cell {
+    fn from(t: core::Tetromino) -> Self {
+        match t {
+            core::Tetromino::I => Cell::I,
+            core::Tetromino::O => Cell::O,
+            core::Tetromino::T => Cell::T,
+            core::Tetromino::J => Cell::J,
+            core::Tetromino::L => Cell::L,
+            core::Tetromino::S => Cell::S,
+            core::Tetromino::Z => Cell::Z,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Orientation {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Orientation {
+    const ALL: [Orientation; 4] = [
+        Orientation::North,
+        Orientation::East,
+        Orientation::South,
+        Orientation::West,
+    ];
+
+    /// turn an offset from north, clockwise
+    fn rotate(&self, (x, y): (i32, i32)) -> (i32, i32) {
+        match self {
+            Orientation::North => (x, y),
+            Orientation::East => (y, -x),
+            Orientation::South => (-x, -y),
+            Orientation::West => (-y, x),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Spin {
+    None,
+    Mini,
+    Full,
+}
+
+/// piece centred as in SRS, `y` counted up from the bottom row
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Location {
+    #[serde(rename = "type")]
+    pub piece: core::Tetromino,
+    pub orientation: Orientation,
+    pub x: i32,
+    pub y: i32,
+}
+
+/// SRS cells of a north facing piece around its center
+fn offsets(t: core::Tetromino) -> [(i32, i32); 4] {
+    match t {
+        core::Tetromino::I => [(-1, 0), (0, 0), (1, 0), (2, 0)],
+        core::Tetromino::O => [(0, 0), (1, 0), (0, 1), (1, 1)],
+        core::Tetromino::T => [(-1, 0), (0, 0), (1, 0), (0, 1)],
+        core::Tetromino::J => [(-1, 1), (-1, 0), (0, 0), (1, 0)],
+        core::Tetromino::L => [(1, 1), (-1, 0), (0, 0), (1, 0)],
+        core::Tetromino::S => [(-1, 0), (0, 0), (0, 1), (1, 1)],
+        core::Tetromino::Z => [(-1, 1), (0, 1), (0, 0), (1, 0)],
+    }
+}
+
+impl Location {
+    /// cells on the TBP board
+    pub fn cells(&self) -> Vec<(i32, i32)> {
+        offsets(self.piece)
+            .iter()
+            .map(|offset| {
+                let (x, y) = self.orientation.rotate(*offset);
+                (self.x + x, self.y + y)
+            })
+            .collect()
+    }
+
+    /// location covering the cells of a board `height` rows high
+    fn from_positions(
+        piece: core::Tetromino,
+        positions: &[core::Position],
+        height: usize,
+    ) -> Option<Self> {
+        let mut target: Vec<(i32, i32)> = positions
+            .iter()
+            .map(|p| (p.x as i32, (height - 1 - p.y) as i32))
+            .collect();
+        target.sort();
+
+        for orientation in Orientation::ALL {
+            let mut shape: Vec<(i32, i32)> = offsets(piece)
+                .iter()
+                .map(|offset| orientation.rotate(*offset))
+                .collect();
+            shape.sort();
+
+            let (x, y) = (target[0].0 - shape[0].0, target[0].1 - shape[0].1);
+            if shape
+                .iter()
+                .map(|(sx, sy)| (sx + x, sy + y))
+                .eq(target.iter().copied())
+            {
+                return Some(Self {
+                    piece,
+                    orientation,
+                    x,
+                    y,
+                });
+            }
+        }
+        None
+    }
+
+    /// cells on a board `height` rows high, sorted from top-left
+    fn positions(&self, height: usize) -> Option<Vec<core::Position>> {
+        let mut positions = Vec::with_capacity(4);
+        for (x, y) in self.cells() {
+            if x < 0 || y < 0 || y as usize >= height {
+                return None;
+            }
+            positions.push(core::Position::new(x as usize, height - 1 - y as usize));
+        }
+        positions.sort_by_key(|p| (p.y, p.x));
+        Some(positions)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Move {
+    pub location: Location,
+    pub spin: Spin,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Start {
+    pub hold: Option<core::Tetromino>,
+    /// the falling piece first, then the next ones
+    pub queue: Vec<core::Tetromino>,
+    pub combo: u32,
+    pub back_to_back: bool,
+    /// rows from the bottom
+    pub board: Vec<Vec<Option<Cell>>>,
+}
+
+/// message sent by the frontend
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FrontendMessage {
+    Rules {},
+    Start(Start),
+    Stop,
+    Suggest,
+    Play { r#move: Move },
+    NewPiece { piece: core::Tetromino },
+    Quit,
+}
+
+/// message sent by the bot
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BotMessage {
+    Info {
+        name: String,
+        version: String,
+        author: String,
+        features: Vec<String>,
+    },
+    Ready,
+    Error {
+        reason: String,
+    },
+    Suggestion {
+        moves: Vec<Move>,
+    },
+}
+
+fn invalid(reason: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason.into())
+}
+
+/// read one message per line, `None` at the end of input
+fn read<T: DeserializeOwned>(reader: &mut impl BufRead) -> io::Result<Option<T>> {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        if !line.trim().is_empty() {
+            return serde_json::from_str(&line)
+                .map(Some)
+                .map_err(|e| invalid(e.to_string()));
+        }
+    }
+}
+
+fn write<T: Serialize>(writer: &mut impl Write, message: &T) -> io::Result<()> {
+    serde_json::to_writer(&mut *writer, message).map_err(|e| invalid(e.to_string()))?;
+    writeln!(writer)?;
+    writer.flush()
+}
+
+fn board_to_cells(board: &core::Board) -> Vec<Vec<Option<Cell>>> {
+    (0..BOARD_HEIGHT)
+        .map(|y| {
+            (0..board.size.x)
+                .map(|x| {
+                    if y >= board.size.y {
+                        return None;
+                    }
+                    match board.state(&core::Position::new(x, board.size.y - 1 - y)) {
+                        core::State::Block(t) => Some(t.into()),
+                        core::State::Garbage => Some(Cell::G),
+                        _ => None,
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn board_from_cells(cells: &[Vec<Option<Cell>>]) -> Option<core::Board> {
+    let mut board = core::Board::new();
+    for (y, row) in cells.iter().enumerate() {
+        if row.len() != board.size.x {
+            return None;
+        }
+        for (x, cell) in row.iter().enumerate() {
+            let state = match cell {
+                None => continue,
+                Some(Cell::I) => core::State::Block(core::Tetromino::I),
+                Some(Cell::O) => core::State::Block(core::Tetromino::O),
+                Some(Cell::T) => core::State::Block(core::Tetromino::T),
+                Some(Cell::J) => core::State::Block(core::Tetromino::J),
+                Some(Cell::L) => core::State::Block(core::Tetromino::L),
+                Some(Cell::S) => core::State::Block(core::Tetromino::S),
+                Some(Cell::Z) => core::State::Block(core::Tetromino::Z),
+                Some(Cell::G) => core::State::Garbage,
+            };
+            if y >= board.size.y {
+                return None;
+            }
+            let p = core::Position::new(x, board.size.y - 1 - y);
+            board = board.set_state(&p, state);
+        }
+    }
+    Some(board)
+}
+
+fn start(game: &Game) -> Start {
+    Start {
+        hold: game.hold(),
+        queue: game
+            .current()
+            .into_iter()
+            .chain(game.next().iter().copied())
+            .collect(),
+        combo: game.combo() as u32,
+        back_to_back: game.back_to_back(),
+        board: board_to_cells(game.board()),
+    }
+}
+
+/// drives an external TBP bot against a `Game`
+pub struct Frontend<R, W> {
+    reader: R,
+    writer: W,
+}
+
+impl Frontend<BufReader<ChildStdout>, ChildStdin> {
+    /// start the bot process, talking over its stdin and stdout
+    pub fn spawn(command: &mut Command) -> io::Result<(Self, Child)> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let writer = child.stdin.take().ok_or_else(|| invalid("no stdin"))?;
+        let reader = BufReader::new(child.stdout.take().ok_or_else(|| invalid("no stdout"))?);
+        Ok((Self::new(reader, writer), child))
+    }
+}
+
+impl<R: BufRead, W: Write> Frontend<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        Self { reader, writer }
+    }
+
+    fn send(&mut self, message: &FrontendMessage) -> io::Result<()> {
+        write(&mut self.writer, message)
+    }
+
+    fn receive(&mut self) -> io::Result<BotMessage> {
+        read(&mut self.reader)?.ok_or_else(|| invalid("bot closed its output"))
+    }
+
+    /// wait for `info`, agree on the rules and return the bot name
+    pub fn handshake(&mut self) -> io::Result<String> {
+        let name = match self.receive()? {
+            BotMessage::Info { name, .. } => name,
+            message => return Err(invalid(format!("expected info, got {message:?}"))),
+        };
+        self.send(&FrontendMessage::Rules {})?;
+        match self.receive()? {
+            BotMessage::Ready => Ok(name),
+            BotMessage::Error { reason } => Err(invalid(reason)),
+            message => Err(invalid(format!("expected ready, got {message:?}"))),
+        }
+    }
+
+    /// let the bot place up to `pieces` blocks
+    ///
+    /// return the game and removed row-count
+    pub fn play(&mut self, game: Game, pieces: usize) -> io::Result<(Game, usize)> {
+        let mut game = match game.current() {
+            Some(_) => game,
+            None => game.advance_to_spawn(),
+        };
+        self.send(&FrontendMessage::Start(start(&game)))?;
+
+        let mut lines = 0;
+        for _ in 0..pieces {
+            if game.is_game_over() {
+                break;
+            }
+
+            self.send(&FrontendMessage::Suggest)?;
+            let moves = match self.receive()? {
+                BotMessage::Suggestion { moves } => moves,
+                message => return Err(invalid(format!("expected suggestion, got {message:?}"))),
+            };
+            // the first move the game allows, the bot may not know its rules
+            let mut error = None;
+            let played =
+                moves
+                    .into_iter()
+                    .find_map(|r#move| match inputs(&game, &r#move.location) {
+                        Ok(inputs) => Some((r#move, inputs)),
+                        Err(err) => {
+                            error.get_or_insert(err);
+                            None
+                        }
+                    });
+            let (r#move, inputs) = match (played, error) {
+                (Some(played), _) => played,
+                (None, Some(err)) => return Err(err),
+                (None, None) => break,
+            };
+
+            let current = game.current().ok_or_else(|| invalid("no falling block"))?;
+            let spawned = match game.hold() {
+                None if r#move.location.piece != current => 2,
+                _ => 1,
+            };
+            for input in inputs {
+                game = game.input(input);
+            }
+            let (locked, count) = game.hard_drop();
+            lines += count;
+            self.send(&FrontendMessage::Play { r#move })?;
+
+            game = locked.advance_to_spawn();
+            let next = game.next();
+            for piece in &next[next.len() - spawned..] {
+                self.send(&FrontendMessage::NewPiece { piece: *piece })?;
+            }
+        }
+
+        self.send(&FrontendMessage::Stop)?;
+        Ok((game, lines))
+    }
+
+    pub fn quit(mut self) -> io::Result<()> {
+        self.send(&FrontendMessage::Quit)
+    }
+}
+
+/// inputs bringing the falling block to `location`
+fn inputs(game: &Game, location: &Location) -> io::Result<Vec<Input>> {
+    let current = game.current().ok_or_else(|| invalid("no falling block"))?;
+    let hold = location.piece != current;
+    if hold {
+        let held = game.hold().or_else(|| game.next().first().copied());
+        if !game.can_hold() || held != Some(location.piece) {
+            return Err(invalid(format!("{:?} is not available", location.piece)));
+        }
+    }
+
+    let target = location
+        .positions(game.size().y)
+        .ok_or_else(|| invalid(format!("{location:?} is outside the board")))?;
+    let placement = movegen::placements_with(game.board(), location.piece, game.ruleset().rotation)
+        .into_iter()
+        .find(|placement| {
+            let mut cells = placement.cells();
+            cells.sort_by_key(|p| (p.y, p.x));
+            cells == target
+        })
+        .ok_or_else(|| invalid(format!("{location:?} can not be reached")))?;
+
+    let mut inputs = Vec::with_capacity(placement.steps.len() + 2);
+    if hold {
+        inputs.push(Input::Hold);
+    }
+    for step in placement.steps {
+        inputs.push(step.into());
+    }
+    inputs.push(movegen::Step::Drop.into());
+    Ok(inputs)
+}
+
+/// answer a TBP frontend with the built-in bot until `quit` or the end of input
+pub fn serve(mut reader: impl BufRead, mut writer: impl Write, bot: &Bot) -> io::Result<()> {
+    write(
+        &mut writer,
+        &BotMessage::Info {
+            name: "tetris-rs".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            author: "a-skua".to_string(),
+            features: Vec::new(),
+        },
+    )?;
+
+    let mut game = None;
+    while let Some(message) = read(&mut reader)? {
+        match message {
+            FrontendMessage::Rules {} => write(&mut writer, &BotMessage::Ready)?,
+            FrontendMessage::Start(start) => game = from_start(&start),
+            FrontendMessage::Stop => game = None,
+            FrontendMessage::Suggest => {
+                let moves = game
+                    .as_ref()
+                    .map(|game| suggest(game, bot))
+                    .unwrap_or_default();
+                write(&mut writer, &BotMessage::Suggestion { moves })?;
+            }
+            FrontendMessage::Play { r#move } => game = game.and_then(|game| play(game, &r#move)),
+            FrontendMessage::NewPiece { piece } => {
+                if let Some(game) = &mut game {
+                    game.next.push(piece);
+                    if game.block.is_none() {
+                        *game = spawn_next(game.clone());
+                    }
+                }
+            }
+            FrontendMessage::Quit => break,
+        }
+    }
+    Ok(())
+}
+
+/// game drawing no piece of its own, the frontend sends them all
+fn from_start(start: &Start) -> Option<Game> {
+    let mut game = Game::with_sequence(&[]);
+    game.board = board_from_cells(&start.board)?;
+    game.hold = start.hold;
+    game.combo = start.combo as usize;
+    game.back_to_back = start.back_to_back;
+    game.next = start.queue.clone();
+    Some(spawn_next(game))
+}
+
+/// spawn the first piece of the queue, never a random one
+fn spawn_next(mut game: Game) -> Game {
+    if game.next.is_empty() {
+        return game;
+    }
+    let t = game.next.remove(0);
+    game.spawn_block(t, &mut ())
+}
+
+/// every move of the bot, best first
+fn suggest(game: &Game, bot: &Bot) -> Vec<Move> {
+    bot.choices(game)
+        .into_iter()
+        .filter_map(|choice| {
+            let location = Location::from_positions(
+                choice.placement.tetromino,
+                &choice.placement.cells(),
+                game.size().y,
+            )?;
+            Some(Move {
+                location,
+                spin: Spin::None,
+            })
+        })
+        .collect()
+}
+
+/// lock the move played by the frontend, `None` if it can not be reached
+fn play(mut game: Game, r#move: &Move) -> Option<Game> {
+    for input in inputs(&game, &r#move.location).ok()? {
+        game = game.input(input);
+    }
+    Some(spawn_next(game.hard_drop().0))
+}
+
+#[cfg(test)]
+mod tbp_tests {
+    use super::*;
+
+    #[test]
+    fn tbp_location() {
+        // north T at the bottom left
+        let location = Location {
+            piece: core::Tetromino::T,
+            orientation: Orientation::North,
+            x: 1,
+            y: 0,
+        };
+        assert_eq!(
+            location.positions(20),
+            Some(vec![
+                core::Position::new(1, 18),
+                core::Position::new(0, 19),
+                core::Position::new(1, 19),
+                core::Position::new(2, 19),
+            ])
+        );
+
+        let board = core::Board::new();
+        for t in [
+            core::Tetromino::I,
+            core::Tetromino::O,
+            core::Tetromino::T,
+            core::Tetromino::J,
+            core::Tetromino::L,
+            core::Tetromino::S,
+            core::Tetromino::Z,
+        ] {
+            for placement in movegen::placements(&board, t) {
+                let mut cells = placement.cells();
+                cells.sort_by_key(|p| (p.y, p.x));
+                let location = Location::from_positions(t, &cells, 20).unwrap();
+                assert_eq!(location.positions(20), Some(cells));
+            }
+        }
+    }
+
+    #[test]
+    fn tbp_messages() {
+        assert_eq!(
+            serde_json::to_string(&FrontendMessage::Suggest).unwrap(),
+            r#"{"type":"suggest"}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&FrontendMessage::Rules {}).unwrap(),
+            r#"{"type":"rules"}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<FrontendMessage>(
+                r#"{"type":"play","move":{"location":{"type":"S","orientation":"east","x":4,"y":1},"spin":"none"}}"#
+            )
+            .unwrap(),
+            FrontendMessage::Play {
+                r#move: Move {
+                    location: Location {
+                        piece: core::Tetromino::S,
+                        orientation: Orientation::East,
+                        x: 4,
+                        y: 1,
+                    },
+                    spin: Spin::None,
+                }
+            }
+        );
+        assert_eq!(
+            serde_json::from_str::<BotMessage>(r#"{"type":"error","reason":"unsupported_rules"}"#)
+                .unwrap(),
+            BotMessage::Error {
+                reason: "unsupported_rules".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn tbp_serve() {
+        let mut game = Game::new();
+        game.next = vec![core::Tetromino::O, core::Tetromino::I, core::Tetromino::T];
+        let game = spawn_next(game);
+
+        let mut input = Vec::new();
+        write(&mut input, &FrontendMessage::Rules {}).unwrap();
+        write(&mut input, &FrontendMessage::Start(start(&game))).unwrap();
+        write(&mut input, &FrontendMessage::Suggest).unwrap();
+        write(&mut input, &FrontendMessage::Quit).unwrap();
+
+        let mut output = Vec::new();
+        serve(&input[..], &mut output, &Bot::default()).unwrap();
+
+        let mut output = &output[..];
+        assert!(matches!(
+            read(&mut output).unwrap(),
+            Some(BotMessage::Info { .. })
+        ));
+        assert_eq!(read(&mut output).unwrap(), Some(BotMessage::Ready));
+        let moves = match read(&mut output).unwrap() {
+            Some(BotMessage::Suggestion { moves }) => moves,
+            message => panic!("{message:?}"),
+        };
+        assert!(moves.len() > 1);
+        assert!(moves.iter().all(|m| inputs(&game, &m.location).is_ok()));
+        assert_eq!(read::<BotMessage>(&mut output).unwrap(), None);
+    }
+
+    #[test]
+    fn tbp_play() {
+        let mut game = Game::with_sequence(&[]);
+        game.next = vec![core::Tetromino::O, core::Tetromino::I];
+        let game = spawn_next(game);
+
+        // O flat on the bottom left, then I is the falling piece
+        let r#move = Move {
+            location: Location {
+                piece: core::Tetromino::O,
+                orientation: Orientation::North,
+                x: 0,
+                y: 0,
+            },
+            spin: Spin::None,
+        };
+        let game = play(game, &r#move).unwrap();
+        assert_eq!(game.current(), Some(core::Tetromino::I));
+        assert_eq!(
+            game.board().state(&core::Position::new(1, 18)),
+            core::State::Block(core::Tetromino::O)
+        );
+
+        // the same O again overlaps the first one
+        assert_eq!(play(game.clone(), &r#move), None);
+
+        // hold I and play the O from the queue next to the first one
+        let mut game = game;
+        game.next.push(core::Tetromino::O);
+        let mut r#move = r#move;
+        r#move.location.x = 2;
+        let game = play(game, &r#move).unwrap();
+        assert_eq!(game.hold(), Some(core::Tetromino::I));
+        assert_eq!(game.current(), None);
+    }
+
+    #[test]
+    fn tbp_play_clear() {
+        // . . . .[][][][][][]
+        let mut game = Game::with_sequence(&[]);
+        for x in 4..10 {
+            let p = core::Position::new(x, 19);
+            game.board = game
+                .board
+                .set_state(&p, core::State::Block(core::Tetromino::O));
+        }
+        game.next = vec![core::Tetromino::I, core::Tetromino::O];
+        let game = spawn_next(game);
+
+        // a flat I floating in the air can not be reached
+        let mut r#move = Move {
+            location: Location {
+                piece: core::Tetromino::I,
+                orientation: Orientation::North,
+                x: 1,
+                y: 5,
+            },
+            spin: Spin::None,
+        };
+        assert_eq!(play(game.clone(), &r#move), None);
+
+        // the I on the bottom left clears the row and starts a combo
+        r#move.location.y = 0;
+        let game = play(game, &r#move).unwrap();
+        assert!(game.board().is_empty());
+        assert_eq!(game.combo(), 1);
+        assert_eq!(start(&game).combo, 1);
+        assert_eq!(game.current(), Some(core::Tetromino::O));
+    }
+}
@@ -0,0 +1,93 @@
+use super::InputRotateDirection;
+use serde::{Deserialize, Serialize};
+
+/// frames between a block locking and the next one spawning
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Delays {
+    /// entry delay after every lock
+    pub are: usize,
+    /// added to the entry delay when the lock cleared lines
+    pub line_clear: usize,
+}
+
+/// where the game is between a lock and the next spawn
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Phase {
+    /// a block is falling, or spawns on the next tick
+    #[default]
+    Falling,
+    /// frames left of the entry delay
+    Entry(usize),
+    /// frames left of the line clear delay, the entry delay follows
+    LineClear(usize),
+}
+
+impl Phase {
+    /// the phase after a lock clearing `lines`
+    pub(crate) fn after(delays: &Delays, lines: usize) -> Self {
+        match (lines, delays.line_clear) {
+            (0, _) | (_, 0) => Self::entry(delays.are),
+            (_, frames) => Self::LineClear(frames),
+        }
+    }
+
+    fn entry(frames: usize) -> Self {
+        match frames {
+            0 => Self::Falling,
+            _ => Self::Entry(frames),
+        }
+    }
+
+    /// advance a frame, return whether the delay ran out
+    pub(crate) fn tick(&mut self, delays: &Delays) -> bool {
+        *self = match *self {
+            Self::Falling => return false,
+            Self::LineClear(1) => Self::entry(delays.are),
+            Self::LineClear(frames) => Self::LineClear(frames - 1),
+            Self::Entry(frames) => Self::entry(frames - 1),
+        };
+        *self == Self::Falling
+    }
+
+    pub fn is_waiting(&self) -> bool {
+        *self != Self::Falling
+    }
+}
+
+/// rotation and hold pressed during a delay, applied when the next block
+/// spawns
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub(crate) struct Buffer {
+    /// initial rotation, the last one pressed
+    pub(crate) rotation: Option<InputRotateDirection>,
+    /// initial hold
+    pub(crate) hold: bool,
+}
+
+#[cfg(test)]
+mod timing_tests {
+    use super::*;
+
+    #[test]
+    fn timing_phase() {
+        let delays = Delays {
+            are: 2,
+            line_clear: 3,
+        };
+        let mut phase = Phase::after(&delays, 1);
+        let ends: Vec<_> = (0..6).map(|_| phase.tick(&delays)).collect();
+        assert_eq!(ends, vec![false, false, false, false, true, false]);
+        assert!(!phase.is_waiting());
+
+        assert_eq!(Phase::after(&Delays::default(), 1), Phase::Falling);
+        let mut phase = Phase::after(
+            &Delays {
+                are: 1,
+                line_clear: 0,
+            },
+            2,
+        );
+        assert!(phase.tick(&delays));
+    }
+}
@@ -0,0 +1,336 @@
+use super::{randomizer, Clear, Event, Game, HoleStrategy, Input, Observer, TSpin};
+use std::collections::VecDeque;
+
+/// rows of garbage sent by a clear
+#[derive(Debug, PartialEq, Clone)]
+pub struct AttackTable {
+    /// by removed rows, 0 to 4
+    pub lines: [usize; 5],
+    /// by rows removed with a T-spin, 0 to 3
+    pub t_spin: [usize; 4],
+    pub t_spin_mini: [usize; 4],
+    /// added by the combo count of the clear, the last value repeats
+    pub combo: Vec<usize>,
+    pub back_to_back: usize,
+    pub all_clear: usize,
+}
+
+impl Default for AttackTable {
+    /// guideline values
+    fn default() -> Self {
+        Self {
+            lines: [0, 0, 1, 2, 4],
+            t_spin: [0, 2, 4, 6],
+            t_spin_mini: [0, 0, 1, 1],
+            combo: vec![0, 0, 1, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5],
+            back_to_back: 1,
+            all_clear: 10,
+        }
+    }
+}
+
+impl AttackTable {
+    pub fn attack(&self, clear: &Clear) -> usize {
+        let rows = clear.kind.rows();
+        let mut attack = match clear.t_spin {
+            None => self.lines[rows],
+            Some(TSpin::Full) => self.t_spin[rows.min(3)],
+            Some(TSpin::Mini) => self.t_spin_mini[rows.min(3)],
+        };
+        attack += self
+            .combo
+            .get(clear.combo)
+            .or(self.combo.last())
+            .copied()
+            .unwrap_or(0);
+        if clear.back_to_back {
+            attack += self.back_to_back;
+        }
+        if clear.all_clear {
+            attack += self.all_clear;
+        }
+        attack
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct Player {
+    game: Game,
+    /// incoming attacks in arrival order, each one becomes rows sharing a hole
+    garbage: VecDeque<usize>,
+    /// last player attacked
+    target: usize,
+}
+
+/// two or more games sending garbage to each other
+#[derive(Debug, PartialEq, Clone)]
+pub struct Match {
+    players: Vec<Player>,
+    table: AttackTable,
+    /// draws the hole columns
+    generator: randomizer::Generator,
+}
+
+impl Match {
+    /// every player gets the same pieces, drawn from `seed`
+    pub fn new(players: usize, table: AttackTable, seed: u64) -> Self {
+        let game = Game::with_randomizer(randomizer::Randomizer::Bag, seed);
+        Self {
+            players: (0..players.max(2))
+                .map(|i| Player {
+                    game: game.clone(),
+                    garbage: VecDeque::new(),
+                    target: i,
+                })
+                .collect(),
+            table,
+            generator: randomizer::Generator::new(randomizer::Randomizer::Random, Some(seed)),
+        }
+    }
+
+    pub fn players(&self) -> usize {
+        self.players.len()
+    }
+
+    pub fn game(&self, player: usize) -> &Game {
+        &self.players[player].game
+    }
+
+    /// incoming rows, not inserted yet
+    pub fn pending(&self, player: usize) -> usize {
+        self.players[player].garbage.iter().sum()
+    }
+
+    pub fn input(&mut self, player: usize, input: Input) {
+        self.step(player, |game, observer| game.input_with(input, observer));
+    }
+
+    /// return removed row-count
+    pub fn deside(&mut self, player: usize) -> usize {
+        let mut count = 0;
+        self.step(player, |game, observer| {
+            let (game, cnt) = game.deside_with(observer);
+            count = cnt;
+            game
+        });
+        count
+    }
+
+    /// update a game, `f` passes the observer on so clears turn into attacks
+    ///
+    /// clears cancel incoming garbage first and send the rest to the next
+    /// opponent in turn; a lock removing no row takes in all the garbage
+    pub fn step(&mut self, player: usize, f: impl FnOnce(Game, &mut dyn Observer) -> Game) {
+        if self.players[player].game.is_game_over() {
+            return;
+        }
+
+        let mut events = Vec::new();
+        let game = f(self.players[player].game.clone(), &mut events);
+        self.players[player].game = game;
+
+        let mut locked = false;
+        let mut cleared = false;
+        for event in &events {
+            match event {
+                Event::PieceLocked { .. } => locked = true,
+                Event::LinesCleared { clear, .. } => {
+                    cleared = true;
+                    let attack = self.table.attack(clear);
+                    self.attack(player, attack);
+                }
+                _ => {}
+            }
+        }
+        if locked && !cleared {
+            self.receive(player);
+        }
+    }
+
+    fn attack(&mut self, player: usize, mut rows: usize) {
+        let garbage = &mut self.players[player].garbage;
+        while rows > 0 {
+            match garbage.front_mut() {
+                Some(incoming) if *incoming > rows => {
+                    *incoming -= rows;
+                    rows = 0;
+                }
+                Some(incoming) => {
+                    rows -= *incoming;
+                    garbage.pop_front();
+                }
+                None => break,
+            }
+        }
+
+        if rows == 0 {
+            return;
+        }
+        if let Some(target) = self.next_target(player) {
+            self.players[player].target = target;
+            self.players[target].garbage.push_back(rows);
+        }
+    }
+
+    /// alive opponent after the last one attacked
+    fn next_target(&self, player: usize) -> Option<usize> {
+        let count = self.players.len();
+        let last = self.players[player].target;
+        (1..=count)
+            .map(|i| (last + i) % count)
+            .find(|i| *i != player && !self.players[*i].game.is_game_over())
+    }
+
+    fn receive(&mut self, player: usize) {
+        while let Some(rows) = self.players[player].garbage.pop_front() {
+            // a new column for each attack, kept by all of its rows
+            let game = &mut self.players[player].game;
+            let (board, top_out) = self.generator.with_rng(|rng| {
+                let (board, first) = game
+                    .board
+                    .clone()
+                    .push_garbage(1, &HoleStrategy::Random, rng);
+                let (board, rest) = board.push_garbage(rows - 1, &HoleStrategy::Same, rng);
+                (board, first || rest)
+            });
+            game.board = board;
+            let blocked = game
+                .block
+                .as_ref()
+                .is_some_and(|block| !block.can_change(&game.board));
+            if top_out || blocked {
+                game.game_over = true;
+                game.block = None;
+                self.players[player].garbage.clear();
+                return;
+            }
+        }
+    }
+
+    /// the last player standing
+    pub fn winner(&self) -> Option<usize> {
+        let mut alive = (0..self.players.len()).filter(|i| !self.players[*i].game.is_game_over());
+        match (alive.next(), alive.next()) {
+            (Some(winner), None) => Some(winner),
+            _ => None,
+        }
+    }
+
+    /// at most one player is left
+    pub fn is_over(&self) -> bool {
+        self.players
+            .iter()
+            .filter(|player| !player.game.is_game_over())
+            .count()
+            <= 1
+    }
+}
+
+#[cfg(test)]
+mod versus_tests {
+    use super::*;
+    use crate::{bot::Bot, core, Block, ClearKind};
+
+    fn clear(kind: ClearKind) -> Clear {
+        Clear {
+            kind,
+            t_spin: None,
+            combo: 0,
+            back_to_back: false,
+            all_clear: false,
+        }
+    }
+
+    #[test]
+    fn versus_attack_table() {
+        let table = AttackTable::default();
+        assert_eq!(table.attack(&clear(ClearKind::Single)), 0);
+        assert_eq!(table.attack(&clear(ClearKind::Tetris)), 4);
+        assert_eq!(
+            table.attack(&Clear {
+                t_spin: Some(TSpin::Full),
+                back_to_back: true,
+                ..clear(ClearKind::Double)
+            }),
+            5
+        );
+        assert_eq!(
+            table.attack(&Clear {
+                combo: 20,
+                ..clear(ClearKind::Double)
+            }),
+            6
+        );
+        assert_eq!(
+            table.attack(&Clear {
+                all_clear: true,
+                ..clear(ClearKind::Single)
+            }),
+            10
+        );
+    }
+
+    /// rows 16 to 19 filled but the last column, the next block an I standing
+    /// there, and a cell left over on row 15
+    fn tetris_ready(game: &mut Game) {
+        game.board = game.board.clone().set_state(
+            &core::Position::new(0, 15),
+            core::State::Block(core::Tetromino::O),
+        );
+        for y in 16..20 {
+            for x in 0..9 {
+                game.board = game.board.clone().set_state(
+                    &core::Position::new(x, y),
+                    core::State::Block(core::Tetromino::O),
+                );
+            }
+        }
+        game.block = Some(Block::new(core::Position::new(9, 16), core::Block::new_i()));
+    }
+
+    #[test]
+    fn versus_garbage() {
+        let mut versus = Match::new(2, AttackTable::default(), 1);
+        versus.deside(1);
+        tetris_ready(&mut versus.players[0].game);
+        assert_eq!(versus.deside(0), 4);
+        assert_eq!(versus.pending(1), 4);
+
+        // player 1 hard drops without clearing and takes the garbage in
+        versus.input(1, crate::movegen::Step::Drop.into());
+        versus.deside(1);
+        assert_eq!(versus.pending(1), 0);
+        let board = versus.game(1).board();
+        let filled = (0..20)
+            .flat_map(|y| (0..10).map(move |x| core::Position::new(x, y)))
+            .filter(|p| board.state(p) != core::State::Empty)
+            .count();
+        assert_eq!(filled, 4 * 9 + 4);
+        assert!(board.metrics().max_height >= 5);
+    }
+
+    #[test]
+    fn versus_cancel() {
+        let mut versus = Match::new(2, AttackTable::default(), 1);
+        versus.players[0].garbage.push_back(3);
+        versus.players[0].garbage.push_back(2);
+        tetris_ready(&mut versus.players[0].game);
+        versus.deside(0);
+        assert_eq!(versus.pending(0), 1);
+        assert_eq!(versus.pending(1), 0);
+    }
+
+    #[test]
+    fn versus_winner() {
+        let bot = Bot::default();
+        let mut versus = Match::new(3, AttackTable::default(), 2);
+        versus.players[1].game.game_over = true;
+        assert_eq!(versus.winner(), None);
+
+        versus.players[2].garbage.push_back(20);
+        versus.step(2, |game, observer| bot.play_with(game, observer).0);
+        assert!(versus.game(2).is_game_over());
+        assert!(versus.is_over());
+        assert_eq!(versus.winner(), Some(0));
+    }
+}
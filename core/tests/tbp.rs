@@ -0,0 +1,53 @@
+use std::process::Command;
+use tetris::ruleset::Ruleset;
+use tetris::tbp::Frontend;
+use tetris::Game;
+
+/// the engine mode of the tbp binary stands in for an external bot
+#[test]
+fn tbp_frontend() {
+    let (mut frontend, mut child) =
+        Frontend::spawn(Command::new(env!("CARGO_BIN_EXE_tbp")).arg("engine")).unwrap();
+    assert_eq!(frontend.handshake().unwrap(), "tetris-rs");
+
+    let (game, lines) = frontend.play(Game::new(), 30).unwrap();
+    frontend.quit().unwrap();
+    assert!(child.wait().unwrap().success());
+
+    assert!(!game.is_game_over());
+    assert!(game.current().is_some());
+    assert!(lines > 0);
+}
+
+/// pieces lock at once on a ruleset with a lock delay
+#[test]
+fn tbp_frontend_lock_delay() {
+    let (mut frontend, mut child) =
+        Frontend::spawn(Command::new(env!("CARGO_BIN_EXE_tbp")).arg("engine")).unwrap();
+    frontend.handshake().unwrap();
+
+    let game = Game::with_ruleset(Ruleset::guideline(), Some(0));
+    let (game, lines) = frontend.play(game, 30).unwrap();
+    frontend.quit().unwrap();
+    assert!(child.wait().unwrap().success());
+
+    assert!(!game.is_game_over());
+    assert!(lines > 0);
+}
+
+/// the next block spawns at once on a ruleset with entry delays
+#[test]
+fn tbp_frontend_delays() {
+    let (mut frontend, mut child) =
+        Frontend::spawn(Command::new(env!("CARGO_BIN_EXE_tbp")).arg("engine")).unwrap();
+    frontend.handshake().unwrap();
+
+    let game = Game::with_ruleset(Ruleset::nes(), Some(0));
+    let (game, lines) = frontend.play(game, 30).unwrap();
+    frontend.quit().unwrap();
+    assert!(child.wait().unwrap().success());
+
+    assert!(!game.is_game_over());
+    assert!(game.current().is_some());
+    assert!(lines > 0);
+}
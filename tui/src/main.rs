@@ -0,0 +1,351 @@
+use crossterm::{
+    cursor,
+    event::{
+        self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    execute, queue,
+    style::{Color, Print, ResetColor, SetForegroundColor},
+    terminal,
+};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+use tetris::handling::Action;
+use tetris::mode::{Endless, Session};
+use tetris::scoreboard::{Entry, FileStorage, Scoreboard};
+use tetris::*;
+
+const FPS: u64 = 30;
+
+/// left edge of the board, leaving room for the hold panel
+const BOARD_X: u16 = 12;
+
+/// `$HOME/.local/share/tetris/scores.json`, or `scores.json` without a home
+fn scores() -> FileStorage {
+    let path = match std::env::var_os("HOME") {
+        Some(home) => PathBuf::from(home).join(".local/share/tetris/scores.json"),
+        None => PathBuf::from("scores.json"),
+    };
+    FileStorage::new(path)
+}
+
+/// puts the terminal in raw mode on an alternate screen until dropped
+struct Screen {
+    /// the terminal reports key releases, so keys can be held
+    releases: bool,
+}
+
+impl Screen {
+    fn new() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        let releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if releases {
+            execute!(
+                io::stdout(),
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+        Ok(Self { releases })
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        if self.releases {
+            let _ = execute!(io::stdout(), PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn color(t: Tetromino) -> Color {
+    match t {
+        Tetromino::I => Color::Cyan,
+        Tetromino::O => Color::Yellow,
+        Tetromino::T => Color::Magenta,
+        Tetromino::J => Color::Blue,
+        Tetromino::L => Color::DarkYellow,
+        Tetromino::S => Color::Green,
+        Tetromino::Z => Color::Red,
+    }
+}
+
+/// tetromino as it spawns, for the hold and next panels
+fn shape(t: Tetromino) -> &'static [&'static str] {
+    match t {
+        Tetromino::I => &["[]", "[]", "[]", "[]"],
+        Tetromino::O => &["[][]", "[][]"],
+        Tetromino::T => &["[][][]", "  []  "],
+        Tetromino::J => &["  []", "  []", "[][]"],
+        Tetromino::L => &["[]  ", "[]  ", "[][]"],
+        Tetromino::S => &["  [][]", "[][]  "],
+        Tetromino::Z => &["[][]  ", "  [][]"],
+    }
+}
+
+/// game action of a key
+fn action(code: KeyCode) -> Option<Action> {
+    let action = match code {
+        KeyCode::Left | KeyCode::Char('h') => Action::Left,
+        KeyCode::Right | KeyCode::Char('l') => Action::Right,
+        KeyCode::Down | KeyCode::Char('j') => Action::SoftDrop,
+        KeyCode::Up | KeyCode::Char('k') | KeyCode::Char(' ') => Action::HardDrop,
+        KeyCode::Char('z') | KeyCode::Char('[') => Action::RotateLeft,
+        KeyCode::Char('x') | KeyCode::Char(']') => Action::RotateRight,
+        KeyCode::Char('a') => Action::Flip,
+        KeyCode::Char('c') => Action::Hold,
+        _ => return None,
+    };
+    Some(action)
+}
+
+struct App {
+    /// the engine clock only runs while the game is not paused
+    session: Session,
+    paused: bool,
+    /// keys are held until released, rather than tapped
+    releases: bool,
+    /// best score on the scoreboard when the game started
+    best: Option<usize>,
+    /// place of the game on the scoreboard, once it is over
+    place: Option<usize>,
+}
+
+impl App {
+    fn new(releases: bool) -> Self {
+        let session = Session::new(Box::new(Endless));
+        let best = Scoreboard::load(&scores()).ok().and_then(|board| {
+            board
+                .personal_best(&session.mode().name())
+                .map(|entry| entry.score)
+        });
+        Self {
+            session,
+            paused: false,
+            releases,
+            best,
+            place: None,
+        }
+    }
+
+    fn game(&self) -> &Game {
+        self.session.game()
+    }
+
+    /// put the game on the scoreboard once it is over, errors lose the entry
+    fn record(&mut self) {
+        if self.place.is_some() {
+            return;
+        }
+        let Some(outcome) = self.session.outcome() else {
+            return;
+        };
+        let date = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let entry = Entry::new(&outcome, date);
+        let mut storage = scores();
+        let Ok(mut board) = Scoreboard::load(&storage) else {
+            return;
+        };
+        let place = board.add(entry);
+        if board.save(&mut storage).is_ok() {
+            self.place = Some(place);
+        }
+    }
+
+    /// return false to quit
+    fn key(&mut self, key: KeyEvent) -> bool {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return false;
+        }
+
+        // only action keys care about repeats and releases
+        if key.kind != KeyEventKind::Press && (self.paused || action(key.code).is_none()) {
+            return true;
+        }
+
+        if self.game().is_game_over() {
+            match key.code {
+                KeyCode::Char('r') => *self = Self::new(self.releases),
+                KeyCode::Char('q') | KeyCode::Esc => return false,
+                _ => {}
+            }
+            return true;
+        }
+
+        if self.paused {
+            match key.code {
+                KeyCode::Char('p') | KeyCode::Esc => self.paused = false,
+                KeyCode::Char('q') => return false,
+                _ => {}
+            }
+            return true;
+        }
+
+        if let Some(action) = action(key.code) {
+            match (self.releases, key.kind) {
+                (true, KeyEventKind::Press) => self.session.input(Input::KeyDown(action)),
+                (true, KeyEventKind::Release) => self.session.input(Input::KeyUp(action)),
+                (true, KeyEventKind::Repeat) => {}
+                // a tap, one row for a soft drop
+                (false, _) if action == Action::SoftDrop => self
+                    .session
+                    .input(Input::Move(InputMove::new(InputMoveDirection::Bottom, 1))),
+                (false, _) => {
+                    self.session.input(Input::KeyDown(action));
+                    self.session.input(Input::KeyUp(action));
+                }
+            }
+            return true;
+        }
+
+        match key.code {
+            KeyCode::Char('p') | KeyCode::Esc => {
+                // held keys would keep shifting once resumed
+                for action in [Action::Left, Action::Right, Action::SoftDrop] {
+                    self.session.input(Input::KeyUp(action));
+                }
+                self.paused = true
+            }
+            KeyCode::Char('q') => return false,
+            _ => {}
+        }
+        true
+    }
+
+    fn render(&self, w: &mut impl Write) -> io::Result<()> {
+        queue!(w, terminal::Clear(terminal::ClearType::All))?;
+
+        let size = self.game().size();
+        for (y, row) in self.game().table().iter().enumerate() {
+            queue!(w, cursor::MoveTo(BOARD_X, y as u16), Print("<!"))?;
+            for state in row {
+                match state {
+                    State::Block(t) => {
+                        queue!(w, SetForegroundColor(color(*t)), Print("[]"), ResetColor)?
+                    }
+                    State::Ghost(t) => {
+                        queue!(w, SetForegroundColor(color(*t)), Print("::"), ResetColor)?
+                    }
+                    State::Garbage => queue!(
+                        w,
+                        SetForegroundColor(Color::DarkGrey),
+                        Print("[]"),
+                        ResetColor
+                    )?,
+                    State::Empty => queue!(w, Print(" ."))?,
+                }
+            }
+            queue!(w, Print("!>"))?;
+        }
+        queue!(
+            w,
+            cursor::MoveTo(BOARD_X, size.y as u16),
+            Print(format!("<!{}!>", "==".repeat(size.x))),
+            cursor::MoveTo(BOARD_X, size.y as u16 + 1),
+            Print(format!("  {}", "\\/".repeat(size.x))),
+        )?;
+
+        queue!(w, cursor::MoveTo(0, 0), Print("HOLD"))?;
+        if let Some(t) = self.game().hold() {
+            self.render_shape(w, t, 0, 1)?;
+        }
+
+        let info = [
+            format!("SCORE {}", self.game().score()),
+            format!("LINES {}", self.game().lines()),
+            format!("LEVEL {}", self.game().level()),
+            match (self.place, self.best) {
+                (Some(place), _) => format!("RANK  {place}"),
+                (None, Some(best)) => format!("BEST  {best}"),
+                (None, None) => "BEST  -".to_string(),
+            },
+        ];
+        for (i, text) in info.iter().enumerate() {
+            queue!(w, cursor::MoveTo(0, 7 + i as u16 * 2), Print(text))?;
+        }
+
+        let next_x = BOARD_X + 2 * size.x as u16 + 6;
+        queue!(w, cursor::MoveTo(next_x, 0), Print("NEXT"))?;
+        for (i, t) in self.game().next().iter().take(3).enumerate() {
+            self.render_shape(w, *t, next_x, 1 + i as u16 * 5)?;
+        }
+
+        let help = [
+            "move    h l / ← →",
+            "drop    j / ↓",
+            "hard    k / ↑ / space",
+            "rotate  z x / [ ]",
+            "180     a",
+            "hold    c",
+            "pause   p",
+            "quit    q",
+        ];
+        for (i, text) in help.iter().enumerate() {
+            queue!(w, cursor::MoveTo(next_x, 16 + i as u16), Print(text))?;
+        }
+
+        let message = if self.game().is_game_over() {
+            Some(["GAME OVER", "r: retry  q: quit"])
+        } else if self.paused {
+            Some(["PAUSE", "p: resume"])
+        } else {
+            None
+        };
+        if let Some(message) = message {
+            for (i, text) in message.iter().enumerate() {
+                let x = BOARD_X + 2 + (2 * size.x as u16).saturating_sub(text.len() as u16) / 2;
+                queue!(
+                    w,
+                    cursor::MoveTo(x, size.y as u16 / 2 - 1 + i as u16 * 2),
+                    Print(text)
+                )?;
+            }
+        }
+
+        w.flush()
+    }
+
+    fn render_shape(&self, w: &mut impl Write, t: Tetromino, x: u16, y: u16) -> io::Result<()> {
+        queue!(w, SetForegroundColor(color(t)))?;
+        for (i, line) in shape(t).iter().enumerate() {
+            queue!(w, cursor::MoveTo(x, y + i as u16), Print(line))?;
+        }
+        queue!(w, ResetColor)
+    }
+}
+
+fn run(releases: bool) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    let mut app = App::new(releases);
+    let mut last = Instant::now();
+
+    loop {
+        app.render(&mut stdout)?;
+
+        if event::poll(Duration::from_millis(1_000 / FPS))? {
+            if let Event::Key(key) = event::read()? {
+                if !app.key(key) {
+                    return Ok(());
+                }
+                app.record();
+            }
+        }
+
+        let now = Instant::now();
+        if !app.paused {
+            app.session.update(now - last);
+            app.record();
+        }
+        last = now;
+    }
+}
+
+fn main() -> io::Result<()> {
+    let screen = Screen::new()?;
+    run(screen.releases)
+}
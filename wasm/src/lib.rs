@@ -1,22 +1,181 @@
+use js_sys::Uint8Array;
 use std::fmt;
+use std::time::Duration;
+use tetris::bot::Bot;
+use tetris::handling::Action;
+use tetris::mode::{Dig, Endless, GameMode, Marathon, Session, Sprint, Ultra};
+use tetris::ruleset::Ruleset;
+use tetris::scoreboard::{Entry, Scoreboard, Storage};
 use tetris::*;
 use wasm_bindgen::prelude::*;
 
+/// `cells` value of a garbage cell
+const CELL_GARBAGE: u8 = 8;
+
 #[wasm_bindgen]
 pub struct Tetris {
-    game: Game,
+    session: Session,
 }
 
 impl fmt::Display for Tetris {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.game.fmt(f)
+        self.session.game().fmt(f)
     }
 }
 
 #[wasm_bindgen]
 pub enum JsState {
     Empty,
+    /// locked on the board
     Block,
+    /// part of the falling block
+    Active,
+    /// where the falling block would land
+    Ghost,
+    /// garbage row pushed in from below
+    Garbage,
+}
+
+#[wasm_bindgen]
+pub enum JsTetromino {
+    I,
+    O,
+    T,
+    J,
+    L,
+    S,
+    Z,
+}
+
+impl From<Tetromino> for JsTetromino {
+    fn from(t: Tetromino) -> Self {
+        match t {
+            Tetromino::I => JsTetromino::I,
+            Tetromino::O => JsTetromino::O,
+            Tetromino::T => JsTetromino::T,
+            Tetromino::J => JsTetromino::J,
+            Tetromino::L => JsTetromino::L,
+            Tetromino::S => JsTetromino::S,
+            Tetromino::Z => JsTetromino::Z,
+        }
+    }
+}
+
+/// what a session did, for the end of game summary
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct JsStats {
+    pub pieces: usize,
+    pub lines: usize,
+    pub singles: usize,
+    pub doubles: usize,
+    pub triples: usize,
+    pub tetrises: usize,
+    pub t_spin_singles: usize,
+    pub t_spin_doubles: usize,
+    pub t_spin_triples: usize,
+    pub t_spin_minis: usize,
+    pub all_clears: usize,
+    pub keys: usize,
+    pub attack: usize,
+    pub max_combo: usize,
+    pub max_back_to_back: usize,
+    pub duration_ms: f64,
+    pub pps: f64,
+    pub kpp: f64,
+    pub apm: f64,
+}
+
+#[wasm_bindgen]
+pub enum JsMode {
+    Endless,
+    /// clear the goal's lines as fast as possible
+    Sprint,
+    /// score as much as possible in the goal's seconds
+    Ultra,
+    /// clear the goal's lines with rising gravity
+    Marathon,
+    /// clear the goal's garbage rows
+    Dig,
+}
+
+/// key held down and repeated by the engine, see `Tetris::key_down`
+#[wasm_bindgen]
+pub enum JsAction {
+    Left,
+    Right,
+    SoftDrop,
+    HardDrop,
+    RotateLeft,
+    RotateRight,
+    Flip,
+    Hold,
+}
+
+impl From<JsAction> for Action {
+    fn from(action: JsAction) -> Self {
+        match action {
+            JsAction::Left => Action::Left,
+            JsAction::Right => Action::Right,
+            JsAction::SoftDrop => Action::SoftDrop,
+            JsAction::HardDrop => Action::HardDrop,
+            JsAction::RotateLeft => Action::RotateLeft,
+            JsAction::RotateRight => Action::RotateRight,
+            JsAction::Flip => Action::Flip,
+            JsAction::Hold => Action::Hold,
+        }
+    }
+}
+
+/// `localStorage` key of the scoreboard
+const SCOREBOARD_KEY: &str = "tetris-scoreboard";
+
+/// the scoreboard in the browser's `localStorage`
+struct LocalStorage;
+
+impl LocalStorage {
+    fn storage() -> Result<web_sys::Storage, String> {
+        web_sys::window()
+            .ok_or("no window")?
+            .local_storage()
+            .map_err(|e| format!("{e:?}"))?
+            .ok_or_else(|| "no localStorage".to_string())
+    }
+}
+
+impl Storage for LocalStorage {
+    fn load(&self) -> Result<Option<String>, String> {
+        Self::storage()?
+            .get_item(SCOREBOARD_KEY)
+            .map_err(|e| format!("{e:?}"))
+    }
+
+    fn save(&mut self, text: &str) -> Result<(), String> {
+        Self::storage()?
+            .set_item(SCOREBOARD_KEY, text)
+            .map_err(|e| format!("{e:?}"))
+    }
+}
+
+/// entries as a JS array of objects
+fn entries_to_js(entries: &[&Entry]) -> Result<JsValue, JsValue> {
+    let json = serde_json::to_string(entries).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    js_sys::JSON::parse(&json)
+}
+
+fn entry_to_js(entry: &Entry) -> Result<JsValue, JsValue> {
+    let json = serde_json::to_string(entry).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    js_sys::JSON::parse(&json)
+}
+
+fn game_mode(mode: JsMode, goal: usize) -> Box<dyn GameMode> {
+    match mode {
+        JsMode::Endless => Box::new(Endless),
+        JsMode::Sprint => Box::new(Sprint::new(goal)),
+        JsMode::Ultra => Box::new(Ultra::new(Duration::from_secs(goal as u64))),
+        JsMode::Marathon => Box::new(Marathon::new(goal)),
+        JsMode::Dig => Box::new(Dig::new(goal)),
+    }
 }
 
 #[wasm_bindgen]
@@ -27,18 +186,82 @@ pub enum JsInput {
     MoveBottom,
     RotateLeft,
     RotateRight,
+    Flip,
+}
+
+impl Default for Tetris {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[wasm_bindgen]
 impl Tetris {
     pub fn new() -> Tetris {
-        Self { game: Game::new() }
+        Self::with_mode(JsMode::Endless, 0)
+    }
+
+    /// `goal` is the line count of a sprint, a marathon or a dig, the
+    /// seconds of an ultra
+    pub fn with_mode(mode: JsMode, goal: usize) -> Tetris {
+        Self {
+            session: Session::new(game_mode(mode, goal)),
+        }
+    }
+
+    /// `with_mode` played by `ruleset`, a preset name or rules in JSON or
+    /// TOML
+    pub fn with_ruleset(mode: JsMode, goal: usize, ruleset: &str) -> Result<Tetris, JsValue> {
+        let ruleset = Ruleset::preset(ruleset)
+            .or_else(|_| Ruleset::parse(ruleset))
+            .map_err(|e| JsValue::from_str(&e))?;
+        let game = Game::with_ruleset(ruleset, None);
+        Ok(Self {
+            session: Session::with_game(game_mode(mode, goal), game),
+        })
+    }
+
+    /// names of the ruleset presets
+    pub fn rulesets() -> Vec<JsValue> {
+        Ruleset::PRESETS
+            .iter()
+            .map(|name| JsValue::from_str(name))
+            .collect()
     }
 
     pub fn deside(&mut self) -> usize {
-        let (game, count) = self.game.clone().deside();
-        self.game = game;
-        count
+        self.session.deside()
+    }
+
+    /// advance the engine clock by `ms`, applying gravity,
+    /// return removed row-count
+    pub fn update(&mut self, ms: f64) -> usize {
+        self.session
+            .update(Duration::from_secs_f64(ms.max(0.0) / 1_000.0))
+    }
+
+    /// let the built-in bot place and lock the falling block,
+    /// return removed row-count
+    pub fn ai_play(&mut self) -> usize {
+        let bot = Bot::default();
+        self.session
+            .step(|game, observer| bot.play_with(game, observer).0)
+    }
+
+    /// cells the bot would place the falling block on, as indexes into
+    /// `cells()`, empty without a falling block
+    pub fn hint(&self) -> Vec<u32> {
+        let choice = match Bot::default().choose(self.session.game()) {
+            Some(choice) => choice,
+            None => return Vec::new(),
+        };
+        let size = self.session.game().size();
+        choice
+            .placement
+            .cells()
+            .iter()
+            .map(|p| p.index(&size) as u32)
+            .collect()
     }
 
     pub fn input(&mut self, input: JsInput) {
@@ -46,29 +269,186 @@ impl Tetris {
             JsInput::MoveLeft => Input::Move(InputMove::new(InputMoveDirection::Left, 1)),
             JsInput::MoveRight => Input::Move(InputMove::new(InputMoveDirection::Right, 1)),
             JsInput::MoveBottom => Input::Move(InputMove::new(InputMoveDirection::Bottom, 1)),
-            JsInput::MoveTop => Input::Move(InputMove::new(InputMoveDirection::Bottom, 20)),
+            JsInput::MoveTop => Input::Move(InputMove::new(InputMoveDirection::Bottom, usize::MAX)),
             JsInput::RotateLeft => Input::Rotate(InputRotate::new(InputRotateDirection::Left, 1)),
             JsInput::RotateRight => Input::Rotate(InputRotate::new(InputRotateDirection::Right, 1)),
+            JsInput::Flip => Input::Rotate(InputRotate::new(InputRotateDirection::Flip, 1)),
         };
-        self.game = self.game.clone().input(input);
+        self.session.input(input);
+    }
+
+    /// press a key, held sideways keys and soft drop repeat on `update`
+    /// by the engine's DAS and ARR, so key repeat events are not needed
+    pub fn key_down(&mut self, action: JsAction) {
+        self.session.input(Input::KeyDown(action.into()));
+    }
+
+    pub fn key_up(&mut self, action: JsAction) {
+        self.session.input(Input::KeyUp(action.into()));
     }
 
+    #[allow(clippy::inherent_to_string_shadow_display)]
     pub fn to_string(&self) -> String {
         format!("{self}")
     }
 
     pub fn size_x(&self) -> usize {
-        self.game.size().x
+        self.session.game().size().x
     }
 
     pub fn size_y(&self) -> usize {
-        self.game.size().y
+        self.session.game().size().y
     }
 
     pub fn state(&self, x: usize, y: usize) -> JsState {
-        match self.game.state(Position::new(x, y)) {
+        if let State::Block(..) = self.session.game().block_state(Position::new(x, y)) {
+            return JsState::Active;
+        }
+        match self.session.game().state(Position::new(x, y)) {
             State::Empty => JsState::Empty,
             State::Block(..) => JsState::Block,
+            State::Ghost(..) => JsState::Ghost,
+            State::Garbage => JsState::Garbage,
+        }
+    }
+
+    /// whole board in one call, row by row, one byte per cell:
+    ///
+    /// - low 4 bits: `0` for empty, `8` for garbage, else `JsTetromino` + 1
+    /// - high 4 bits: `0x00` locked on the board, `0x10` falling block,
+    ///   `0x20` ghost
+    pub fn cells(&self) -> Uint8Array {
+        let size = self.session.game().size();
+        let mut cells = Vec::with_capacity(size.x * size.y);
+        for (y, row) in self.session.game().table().iter().enumerate() {
+            for (x, state) in row.iter().enumerate() {
+                let t = match state {
+                    State::Empty => {
+                        cells.push(0);
+                        continue;
+                    }
+                    State::Ghost(t) => {
+                        cells.push(0x20 | (JsTetromino::from(*t) as u8 + 1));
+                        continue;
+                    }
+                    State::Garbage => {
+                        cells.push(CELL_GARBAGE);
+                        continue;
+                    }
+                    State::Block(t) => JsTetromino::from(*t) as u8 + 1,
+                };
+                let kind = match self.session.game().block_state(Position::new(x, y)) {
+                    State::Block(..) => 0x10,
+                    _ => 0x00,
+                };
+                cells.push(kind | t);
+            }
+        }
+        Uint8Array::from(&cells[..])
+    }
+
+    /// tetromino occupying the cell, undefined if it is empty
+    pub fn tetromino_at(&self, x: usize, y: usize) -> Option<JsTetromino> {
+        match self.session.game().state(Position::new(x, y)) {
+            State::Empty | State::Garbage => None,
+            State::Block(t) | State::Ghost(t) => Some(t.into()),
         }
     }
+
+    pub fn lines(&self) -> usize {
+        self.session.game().lines()
+    }
+
+    pub fn score(&self) -> usize {
+        self.session.game().score()
+    }
+
+    /// locked blocks
+    pub fn pieces(&self) -> usize {
+        self.session.progress().pieces
+    }
+
+    /// engine clock in milliseconds, stopped once the session is over
+    pub fn elapsed_ms(&self) -> f64 {
+        self.session.progress().elapsed.as_secs_f64() * 1_000.0
+    }
+
+    /// pieces per second
+    pub fn pps(&self) -> f64 {
+        self.session.progress().pps()
+    }
+
+    pub fn stats(&self) -> JsStats {
+        let stats = self.session.stats();
+        JsStats {
+            pieces: stats.pieces,
+            lines: stats.lines,
+            singles: stats.clears.singles,
+            doubles: stats.clears.doubles,
+            triples: stats.clears.triples,
+            tetrises: stats.clears.tetrises,
+            t_spin_singles: stats.clears.t_spin_singles,
+            t_spin_doubles: stats.clears.t_spin_doubles,
+            t_spin_triples: stats.clears.t_spin_triples,
+            t_spin_minis: stats.clears.t_spin_minis,
+            all_clears: stats.clears.all_clears,
+            keys: stats.keys,
+            attack: stats.attack,
+            max_combo: stats.max_combo,
+            max_back_to_back: stats.max_back_to_back,
+            duration_ms: stats.duration.as_secs_f64() * 1_000.0,
+            pps: stats.pps(),
+            kpp: stats.kpp(),
+            apm: stats.apm(),
+        }
+    }
+
+    /// locked blocks of a tetromino, `JsTetromino` follows the order of
+    /// `Tetromino`
+    pub fn pieces_of(&self, t: JsTetromino) -> usize {
+        self.session.stats().distribution[t as usize]
+    }
+
+    /// put the ended session on the scoreboard in `localStorage`, return
+    /// its place in the ranking of the mode from 1, undefined while playing
+    pub fn save_score(&self) -> Result<Option<usize>, JsValue> {
+        let Some(outcome) = self.session.outcome() else {
+            return Ok(None);
+        };
+        let date = (js_sys::Date::now() / 1_000.0) as u64;
+        let mut storage = LocalStorage;
+        let mut board = Scoreboard::load(&storage).map_err(|e| JsValue::from_str(&e))?;
+        let place = board.add(Entry::new(&outcome, date));
+        board
+            .save(&mut storage)
+            .map_err(|e| JsValue::from_str(&e))?;
+        Ok(Some(place))
+    }
+
+    /// the best `limit` entries of the mode, best first
+    pub fn ranking(&self, limit: usize) -> Result<JsValue, JsValue> {
+        let board = Scoreboard::load(&LocalStorage).map_err(|e| JsValue::from_str(&e))?;
+        let mut ranking = board.ranking(&self.session.mode().name());
+        ranking.truncate(limit);
+        entries_to_js(&ranking)
+    }
+
+    /// the best entry of the mode, null if there is none
+    pub fn personal_best(&self) -> Result<JsValue, JsValue> {
+        let board = Scoreboard::load(&LocalStorage).map_err(|e| JsValue::from_str(&e))?;
+        match board.personal_best(&self.session.mode().name()) {
+            Some(entry) => entry_to_js(entry),
+            None => Ok(JsValue::NULL),
+        }
+    }
+
+    /// the mode's goal is reached
+    pub fn is_finished(&self) -> bool {
+        self.session.is_finished()
+    }
+
+    /// finished or game over
+    pub fn is_over(&self) -> bool {
+        self.session.is_over()
+    }
 }
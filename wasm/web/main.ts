@@ -1,37 +1,138 @@
-import init, { JsInput, JsState, Tetris } from "./wasm/wasm.js";
+import init, { JsAction, JsInput, JsMode, JsTetromino, Tetris } from "./wasm/wasm.js";
 
 const block_size = 20;
 const fps = 30;
 
+const colors = new Map<JsTetromino, string>([
+  [JsTetromino.I, "#00bcd4"],
+  [JsTetromino.O, "#fbc02d"],
+  [JsTetromino.T, "#9c27b0"],
+  [JsTetromino.J, "#3f51b5"],
+  [JsTetromino.L, "#ff9800"],
+  [JsTetromino.S, "#4caf50"],
+  [JsTetromino.Z, "#f44336"],
+]);
+
+// byte encoding of `Tetris.cells()`
+const cell_tetromino = 0x0f;
+const cell_ghost = 0x20;
+const cell_garbage = 8;
+const garbage_color = "#9e9e9e";
+
 const rendering = (tetris: Tetris, ctx: CanvasRenderingContext2D) => {
-  ctx.clearRect(
-    0,
-    0,
-    tetris.size_x() * block_size,
-    tetris.size_y() * block_size,
-  );
-  for (let y = 0; y < tetris.size_y(); y++) {
-    for (let x = 0; x < tetris.size_x(); x++) {
-      const state = tetris.state(x, y);
-      switch (state) {
-        case JsState.Block:
-          ctx.fillRect(x * block_size, y * block_size, block_size, block_size);
-          break;
-        default:
-          ctx.fillRect(
-            (x * block_size) + (block_size / 2),
-            (y * block_size) + (block_size / 2),
-            1,
-            1,
-          );
+  const size_x = tetris.size_x();
+  const size_y = tetris.size_y();
+  ctx.clearRect(0, 0, size_x * block_size, size_y * block_size);
+
+  const cells = tetris.cells();
+  for (let y = 0; y < size_y; y++) {
+    for (let x = 0; x < size_x; x++) {
+      const cell = cells[y * size_x + x];
+      if (cell & cell_tetromino) {
+        const tetromino = (cell & cell_tetromino) - 1 as JsTetromino;
+        ctx.fillStyle = cell === cell_garbage
+          ? garbage_color
+          : colors.get(tetromino) ?? "black";
+        ctx.globalAlpha = (cell & cell_ghost) ? 0.3 : 1;
+        ctx.fillRect(x * block_size, y * block_size, block_size, block_size);
+        ctx.globalAlpha = 1;
+      } else {
+        ctx.fillStyle = "black";
+        ctx.fillRect(
+          (x * block_size) + (block_size / 2),
+          (y * block_size) + (block_size / 2),
+          1,
+          1,
+        );
       }
     }
   }
-  console.log(tetris.to_string());
 };
 
-const createPointElement = (point: number) =>
-  document.createTextNode(`point: ${point}`);
+// selectable modes with their goal, lines or seconds for an ultra
+const modes: [string, JsMode, number][] = [
+  ["Endless", JsMode.Endless, 0],
+  ["Sprint 20L", JsMode.Sprint, 20],
+  ["Sprint 40L", JsMode.Sprint, 40],
+  ["Sprint 100L", JsMode.Sprint, 100],
+  ["Ultra 2min", JsMode.Ultra, 120],
+  ["Ultra 3min", JsMode.Ultra, 180],
+  ["Marathon 150L", JsMode.Marathon, 150],
+  ["Marathon 200L", JsMode.Marathon, 200],
+  ["Dig 10L", JsMode.Dig, 10],
+  ["Dig 18L", JsMode.Dig, 18],
+  ["Dig 100L", JsMode.Dig, 100],
+];
+
+const tetrominos: [string, JsTetromino][] = [
+  ["I", JsTetromino.I],
+  ["O", JsTetromino.O],
+  ["T", JsTetromino.T],
+  ["J", JsTetromino.J],
+  ["L", JsTetromino.L],
+  ["S", JsTetromino.S],
+  ["Z", JsTetromino.Z],
+];
+
+// scoreboard entry as saved by `Tetris.save_score`
+type Entry = {
+  metric: "time" | "score";
+  finished: boolean;
+  score: number;
+  lines: number;
+  time: number;
+};
+
+// the place of an ended session on the scoreboard and the mode's best
+type Record = { place?: number; best: Entry | null };
+
+const formatEntry = (entry: Entry) =>
+  entry.metric === "time" && entry.finished
+    ? `${(entry.time / 1_000).toFixed(2)}s`
+    : entry.metric === "time"
+    ? `${entry.lines} lines`
+    : `${entry.score}`;
+
+const createSummaryElement = (tetris: Tetris, record?: Record) => {
+  const stats = tetris.stats();
+  const pieces = tetrominos
+    .map(([name, t]) => `${name} ${tetris.pieces_of(t)}`)
+    .join(" / ");
+  const lines = [
+    tetris.is_finished() ? "finished" : "game over",
+    `time: ${(stats.duration_ms / 1_000).toFixed(2)}s / score: ${tetris.score()}`,
+    `pieces: ${stats.pieces} (${pieces})`,
+    `pps: ${stats.pps.toFixed(2)} / kpp: ${stats.kpp.toFixed(2)} / apm: ${stats.apm.toFixed(1)}`,
+    `lines: ${stats.lines} / single ${stats.singles} / double ${stats.doubles} / triple ${stats.triples} / tetris ${stats.tetrises}`,
+    `t-spin single ${stats.t_spin_singles} / double ${stats.t_spin_doubles} / triple ${stats.t_spin_triples} / mini ${stats.t_spin_minis}`,
+    `all clear: ${stats.all_clears}`,
+    `max combo: ${stats.max_combo} / max back-to-back: ${stats.max_back_to_back}`,
+  ];
+  if (record?.place !== undefined) {
+    lines.push(`rank: #${record.place}`);
+  }
+  if (record?.best) {
+    lines.push(`personal best: ${formatEntry(record.best)}`);
+  }
+  stats.free();
+  const summary = document.createElement("div");
+  lines.forEach((line) => {
+    summary.appendChild(document.createTextNode(line));
+    summary.appendChild(document.createElement("br"));
+  });
+  return summary;
+};
+
+const createStatusElement = (tetris: Tetris, record?: Record): Node => {
+  if (tetris.is_over()) {
+    return createSummaryElement(tetris, record);
+  }
+  const time = (tetris.elapsed_ms() / 1_000).toFixed(2);
+  const pps = tetris.pps().toFixed(2);
+  return document.createTextNode(
+    `lines: ${tetris.lines()} / score: ${tetris.score()} / time: ${time}s / pps: ${pps}`,
+  );
+};
 
 const createButtonElement = (name: string, f: (e: Event) => void) => {
   const button = document.createElement("button");
@@ -43,7 +144,8 @@ const createButtonElement = (name: string, f: (e: Event) => void) => {
 
 export default () =>
   init().then(() => {
-    const tetris = Tetris.new();
+    let tetris = Tetris.new();
+    let record: Record | undefined;
 
     const canvas = document.createElement("canvas");
     canvas.width = tetris.size_x() * block_size;
@@ -70,6 +172,45 @@ export default () =>
     ctrl.appendChild(
       createButtonElement("]", () => tetris.input(JsInput.RotateRight)),
     );
+    ctrl.appendChild(
+      createButtonElement("180", () => tetris.input(JsInput.Flip)),
+    );
+    let ai = false;
+    ctrl.appendChild(
+      createButtonElement("AI", (e: Event) => {
+        ai = !ai;
+        (e.target as HTMLElement).style.setProperty(
+          "font-weight",
+          ai ? "bold" : "normal",
+        );
+      }),
+    );
+    const select = document.createElement("select");
+    modes.forEach(([name], i) => {
+      const option = document.createElement("option");
+      option.value = `${i}`;
+      option.appendChild(document.createTextNode(name));
+      select.appendChild(option);
+    });
+    const rules = document.createElement("select");
+    Tetris.rulesets().forEach((name: string) => {
+      const option = document.createElement("option");
+      option.value = name;
+      option.appendChild(document.createTextNode(name));
+      rules.appendChild(option);
+    });
+    const restart = () => {
+      const [, mode, goal] = modes[Number(select.value)];
+      tetris.free();
+      tetris = Tetris.with_ruleset(mode, goal, rules.value);
+      record = undefined;
+      canvas.width = tetris.size_x() * block_size;
+      canvas.height = tetris.size_y() * block_size;
+    };
+    select.addEventListener("change", restart);
+    rules.addEventListener("change", restart);
+    ctrl.appendChild(select);
+    ctrl.appendChild(rules);
     document.body.appendChild(ctrl);
 
     const info = document.createElement("div");
@@ -79,8 +220,7 @@ export default () =>
     info.appendChild(document.createElement("br"));
     document.body.appendChild(info);
 
-    let count = 0;
-    info.appendChild(createPointElement(count));
+    info.appendChild(createStatusElement(tetris));
 
     setInterval(
       () =>
@@ -88,39 +228,73 @@ export default () =>
       1_000 / fps,
     );
 
+    // the engine clock follows real time, the bot plays once a second
+    let last = performance.now();
+    let since_ai = 0;
     setInterval(() => {
-      count += tetris.deside();
+      const now = performance.now();
+      const delta = now - last;
+      last = now;
+      if (ai) {
+        since_ai += delta;
+        if (since_ai >= 1_000) {
+          since_ai -= 1_000;
+          tetris.ai_play();
+        }
+      }
+      tetris.update(delta);
+      // saved once, when the session ends
+      if (tetris.is_over() && record === undefined) {
+        record = { place: tetris.save_score(), best: tetris.personal_best() };
+      }
       info.removeChild(info.lastChild);
-      info.appendChild(createPointElement(count));
-    }, 1_000);
+      info.appendChild(createStatusElement(tetris, record));
+    }, 1_000 / fps);
 
-    self.window.addEventListener("keydown", (e: KeyboardEvent) => {
-      console.log(e.key);
-      switch (e.key) {
+    const action = (key: string): JsAction | undefined => {
+      switch (key) {
         case "ArrowLeft":
         case "h":
-          tetris.input(JsInput.MoveLeft);
-          break;
+          return JsAction.Left;
         case "ArrowDown":
         case "j":
-          tetris.input(JsInput.MoveBottom);
-          break;
-        case "ArrowUp":
-        case "k":
-          tetris.input(JsInput.MoveTop);
-          break;
+          return JsAction.SoftDrop;
         case "ArrowRight":
         case "l":
-          tetris.input(JsInput.MoveRight);
-          break;
+          return JsAction.Right;
+        case " ":
+          return JsAction.HardDrop;
         case "[":
         case "p":
-          tetris.input(JsInput.RotateLeft);
-          break;
+          return JsAction.RotateLeft;
         case "]":
         case "n":
-          tetris.input(JsInput.RotateRight);
-          break;
+          return JsAction.RotateRight;
+        case "a":
+          return JsAction.Flip;
+        case "c":
+          return JsAction.Hold;
+      }
+    };
+
+    // the engine repeats held keys itself, so skip the browser's repeats
+    self.window.addEventListener("keydown", (e: KeyboardEvent) => {
+      if (e.repeat) {
+        return;
+      }
+      if (e.key === "ArrowUp" || e.key === "k") {
+        tetris.input(JsInput.MoveTop);
+        return;
+      }
+      const pressed = action(e.key);
+      if (pressed !== undefined) {
+        tetris.key_down(pressed);
+      }
+    });
+    self.window.addEventListener("keyup", (e: KeyboardEvent) => {
+      const released = action(e.key);
+      if (released !== undefined) {
+        tetris.key_up(released);
       }
     });
   });